@@ -9,6 +9,8 @@ Release: @@RELEASE@@%{?dist}
 License: MIT
 Group: Applications/System
 Source0: %{name}-%{version}.tar.gz
+# shallow fetch of tags, libgit2 fetches full history without it
+Recommends: git >= 2.31
 
 BuildRoot: %{_tmppath}/%{name}-%{version}-%{release}-root
 
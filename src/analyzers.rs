@@ -1,7 +1,17 @@
-use spdx_rs::models::{Checksum, FileInformation, SimpleExpression, SpdxExpressionError};
+use std::{
+    fmt::{self, Display},
+    sync::Arc,
+};
 
+use spdx_rs::models::{Checksum, FileInformation, SpdxExpression};
+
+mod checksum;
 mod license;
-mod sha1;
+
+pub use self::{
+    checksum::{ChecksumAlgorithm, ChecksumWriter},
+    license::TARGET as LICENSE_TAG,
+};
 
 pub trait FileAnalyzer: std::io::Write {
     type Output;
@@ -9,42 +19,142 @@ pub trait FileAnalyzer: std::io::Write {
     fn finish(self) -> Self::Output;
 }
 
+/// analyzer registered to `AnalyzerRegistry`, contributes to analyzed file record
+pub trait RecordAnalyzer: std::io::Write + Send {
+    fn contribute(
+        self: Box<Self>,
+        record: &mut SpdxFileAnalyzeSuccess,
+    ) -> Result<(), SpdxFileAnalyzeError>;
+}
+
+const NOASSERTION: &str = "NOASSERTION";
+
+type AnalyzerFactory = dyn Fn() -> Box<dyn RecordAnalyzer> + Send + Sync;
+
+/// analyzers applied to each file, in addition to checksums which are always computed
+///
+/// default registry has `license` analyzer.
+#[derive(Clone)]
+pub struct AnalyzerRegistry {
+    algorithms: Vec<ChecksumAlgorithm>,
+    analyzers: Vec<(String, Arc<AnalyzerFactory>)>,
+}
+
 pub struct SpdxFileAnalyzer {
-    license_analyzer: license::SourceLicenceAnalyzer,
-    sha1_analyzer: sha1::Sha1Writer,
+    checksum_analyzer: checksum::ChecksumWriter,
+    /// registered name and analyzer
+    analyzers: Vec<(String, Box<dyn RecordAnalyzer>)>,
+}
+/// license found in file
+#[derive(Debug, Clone)]
+pub enum FileLicense {
+    Expression(SpdxExpression),
+    /// license tag is malformed
+    NoAssertion,
+}
+
+impl Display for FileLicense {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileLicense::Expression(expression) => write!(f, "{}", expression),
+            FileLicense::NoAssertion => write!(f, "{}", NOASSERTION),
+        }
+    }
 }
+
 pub struct SpdxFileAnalyzeSuccess {
-    license_information_in_file: Option<SimpleExpression>,
-    sha1_checksum: Checksum,
+    license_information_in_file: Option<FileLicense>,
+    /// weakest first, always contains SHA1 and SHA256
+    checksums: Vec<(ChecksumAlgorithm, Checksum)>,
+    comments: Vec<String>,
+    /// problems found in file which do not fail analysis, e.g. malformed license tag
+    warnings: Vec<String>,
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum SpdxFileAnalyzeError {
-    #[error("parse license failed: {0}")]
-    LicenseParseError(#[from] SpdxExpressionError),
     #[error("file io error {0}")]
     IoError(#[from] std::io::Error),
+    #[error("analyzer {0} failed: {1}")]
+    Analyzer(String, Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl Default for AnalyzerRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(
+            "license",
+            || Box::new(license::SourceLicenceAnalyzer::new()),
+        );
+        registry
+    }
 }
 
-impl SpdxFileAnalyzer {
-    pub fn new() -> Self {
+impl AnalyzerRegistry {
+    /// registry without analyzers, only checksums are computed
+    pub fn empty() -> Self {
         Self {
-            license_analyzer: license::SourceLicenceAnalyzer::new(),
-            sha1_analyzer: sha1::Sha1Writer::new(),
+            algorithms: Vec::new(),
+            analyzers: Vec::new(),
+        }
+    }
+
+    /// register analyzer, `factory` creates analyzer for each file
+    pub fn register<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn() -> Box<dyn RecordAnalyzer> + Send + Sync + 'static,
+    {
+        self.analyzers.push((name.to_owned(), Arc::new(factory)));
+    }
+
+    /// remove analyzer by name, returns false if not registered
+    pub fn disable(&mut self, name: &str) -> bool {
+        let len = self.analyzers.len();
+        self.analyzers.retain(|(registered, _)| registered != name);
+        self.analyzers.len() != len
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.analyzers.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// checksum algorithms computed in addition to SHA1 and SHA256
+    pub fn checksum_algorithms(&mut self, algorithms: &[ChecksumAlgorithm]) {
+        self.algorithms = algorithms.to_vec();
+    }
+
+    /// checksums of whole package
+    pub fn checksum_writer(&self) -> ChecksumWriter {
+        checksum::ChecksumWriter::new(&self.algorithms)
+    }
+
+    /// analyzer for one file
+    pub fn file_analyzer(&self) -> SpdxFileAnalyzer {
+        SpdxFileAnalyzer {
+            checksum_analyzer: checksum::ChecksumWriter::new(&self.algorithms),
+            analyzers: self
+                .analyzers
+                .iter()
+                .map(|(name, factory)| (name.clone(), factory()))
+                .collect(),
         }
     }
 }
 
 impl std::io::Write for SpdxFileAnalyzer {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.license_analyzer.write(buf)?;
-        self.sha1_analyzer.write(buf)?;
+        self.checksum_analyzer.write_all(buf)?;
+        for (_, analyzer) in &mut self.analyzers {
+            analyzer.write_all(buf)?;
+        }
         Ok(buf.len())
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
-        self.license_analyzer.flush()?;
-        self.sha1_analyzer.flush()?;
+        self.checksum_analyzer.flush()?;
+        for (_, analyzer) in &mut self.analyzers {
+            analyzer.flush()?;
+        }
         Ok(())
     }
 }
@@ -53,13 +163,18 @@ impl FileAnalyzer for SpdxFileAnalyzer {
     type Output = Result<SpdxFileAnalyzeSuccess, SpdxFileAnalyzeError>;
 
     fn finish(self) -> Self::Output {
-        let license_information_in_file = self.license_analyzer.finish()?;
-        let sha1_checksum = self.sha1_analyzer.finish();
-
-        Ok(SpdxFileAnalyzeSuccess {
-            license_information_in_file,
-            sha1_checksum,
-        })
+        let mut record = SpdxFileAnalyzeSuccess {
+            license_information_in_file: None,
+            checksums: self.checksum_analyzer.finish(),
+            comments: Vec::new(),
+            warnings: Vec::new(),
+        };
+        for (name, analyzer) in self.analyzers {
+            analyzer
+                .contribute(&mut record)
+                .map_err(|e| SpdxFileAnalyzeError::Analyzer(name, Box::new(e)))?;
+        }
+        Ok(record)
     }
 }
 
@@ -68,20 +183,227 @@ impl SpdxFileAnalyzeSuccess {
         self,
         file_info: &mut FileInformation,
     ) -> Result<(), SpdxFileAnalyzeError> {
-        if let Some(license_expression) = self.license_information_in_file {
-            file_info
-                .license_information_in_file
-                .push(license_expression);
+        self.apply_license_to_file_info(file_info);
+        file_info
+            .file_checksum
+            .extend(self.checksums.into_iter().map(|(_, checksum)| checksum));
+        if !self.comments.is_empty() {
+            file_info.file_comment = Some(self.comments.join("\n"));
         }
-        file_info.file_checksum.push(self.sha1_checksum);
         Ok(())
     }
 
+    pub fn checksums(&self) -> impl Iterator<Item = Checksum> + '_ {
+        self.checksums.iter().map(|(_, checksum)| checksum.clone())
+    }
+
+    pub fn checksum(&self, algorithm: ChecksumAlgorithm) -> Option<&Checksum> {
+        self.checksums
+            .iter()
+            .find(|(a, _)| *a == algorithm)
+            .map(|(_, checksum)| checksum)
+    }
+
     pub fn sha1_checksum(&self) -> &Checksum {
-        &self.sha1_checksum
+        self.checksum(ChecksumAlgorithm::Sha1)
+            .expect("SHA1 is always computed")
     }
 
-    pub fn license_information_in_file(&self) -> &Option<SimpleExpression> {
+    pub fn sha256_checksum(&self) -> &Checksum {
+        self.checksum(ChecksumAlgorithm::Sha256)
+            .expect("SHA256 is always computed")
+    }
+
+    /// checksums of the strongest algorithm computed for both files
+    pub fn strongest_common_checksum<'a>(
+        &'a self,
+        other: &'a SpdxFileAnalyzeSuccess,
+    ) -> (&'a Checksum, &'a Checksum) {
+        self.checksums
+            .iter()
+            .rev()
+            .find_map(|(algorithm, checksum)| Some((checksum, other.checksum(*algorithm)?)))
+            .unwrap_or((self.sha1_checksum(), other.sha1_checksum()))
+    }
+
+    pub fn license_information_in_file(&self) -> &Option<FileLicense> {
         &self.license_information_in_file
     }
+
+    /// licenses in `LicenseInfoInFile`, and full expression in license comment
+    /// as `SPDX-License-Identifier: <expression>`, as `LicenseInfoInFile` can not
+    /// represent `OR`, `AND` and `WITH`
+    pub fn apply_license_to_file_info(&self, file_info: &mut FileInformation) {
+        file_info
+            .license_information_in_file
+            .extend(self.licenses_in_file());
+        if let Some(FileLicense::Expression(expression)) = &self.license_information_in_file {
+            file_info.comments_on_license = Some(format!("{} {}", LICENSE_TAG, expression));
+        }
+    }
+
+    /// licenses referenced by license expression, as listed in `LicenseInfoInFile`
+    pub fn licenses_in_file(&self) -> Vec<SpdxExpression> {
+        let mut licenses: Vec<SpdxExpression> = match &self.license_information_in_file {
+            // simple expression of parsed expression is valid expression by itself
+            Some(FileLicense::Expression(expression)) => expression
+                .licenses()
+                .into_iter()
+                .filter_map(|license| SpdxExpression::parse(&license.to_string()).ok())
+                .collect(),
+            // default expression is NOASSERTION
+            Some(FileLicense::NoAssertion) => vec![SpdxExpression::default()],
+            None => Vec::new(),
+        };
+        licenses.sort_by_key(|license| license.to_string());
+        licenses
+    }
+
+    pub fn set_license_information_in_file(&mut self, license: FileLicense) {
+        self.license_information_in_file = Some(license);
+    }
+
+    /// free form findings of analyzers, written as file comment
+    pub fn comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    pub fn push_comment(&mut self, comment: String) {
+        self.comments.push(comment);
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn push_warning(&mut self, warning: String) {
+        self.warnings.push(warning);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use spdx_rs::models::FileInformation;
+
+    use crate::analyzers::{
+        AnalyzerRegistry, FileAnalyzer, RecordAnalyzer, SpdxFileAnalyzeError,
+        SpdxFileAnalyzeSuccess,
+    };
+
+    /// counts bytes, as example of custom analyzer
+    struct SizeAnalyzer(usize);
+
+    impl Write for SizeAnalyzer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl RecordAnalyzer for SizeAnalyzer {
+        fn contribute(
+            self: Box<Self>,
+            record: &mut SpdxFileAnalyzeSuccess,
+        ) -> Result<(), SpdxFileAnalyzeError> {
+            record.push_comment(format!("size {}", self.0));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = AnalyzerRegistry::default();
+        registry.register("size", || Box::new(SizeAnalyzer(0)));
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            vec!["license", "size"]
+        );
+
+        let mut analyzer = registry.file_analyzer();
+        analyzer
+            .write_all(b"// SPDX-License-Identifier: MIT\n")
+            .unwrap();
+        let record = analyzer.finish().unwrap();
+        assert_eq!(
+            record
+                .license_information_in_file()
+                .as_ref()
+                .unwrap()
+                .to_string(),
+            "MIT"
+        );
+        assert_eq!(record.comments(), ["size 32"]);
+
+        assert!(registry.disable("license"));
+        assert!(!registry.disable("license"));
+        let mut analyzer = registry.file_analyzer();
+        analyzer
+            .write_all(b"// SPDX-License-Identifier: MIT\n")
+            .unwrap();
+        let record = analyzer.finish().unwrap();
+        assert!(record.license_information_in_file().is_none());
+        assert_eq!(
+            record.sha1_checksum().value,
+            "4c9f2b0ba719d3e0d974753e4b6c828d6dfd2283"
+        );
+    }
+
+    /// rejects every file
+    struct RejectAnalyzer;
+
+    impl Write for RejectAnalyzer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl RecordAnalyzer for RejectAnalyzer {
+        fn contribute(
+            self: Box<Self>,
+            _record: &mut SpdxFileAnalyzeSuccess,
+        ) -> Result<(), SpdxFileAnalyzeError> {
+            Err(SpdxFileAnalyzeError::IoError(std::io::Error::other(
+                "rejected",
+            )))
+        }
+    }
+
+    #[test]
+    fn test_analyzer_error_has_name() {
+        let mut registry = AnalyzerRegistry::empty();
+        registry.register("reject", || Box::new(RejectAnalyzer));
+        let error = registry.file_analyzer().finish().err().unwrap();
+        assert!(matches!(&error, SpdxFileAnalyzeError::Analyzer(name, _) if name == "reject"));
+    }
+
+    #[test]
+    fn test_apply_license_expression() {
+        let mut analyzer = AnalyzerRegistry::default().file_analyzer();
+        analyzer
+            .write_all(b"// SPDX-License-Identifier: MIT OR Apache-2.0\n")
+            .unwrap();
+        let record = analyzer.finish().unwrap();
+        let mut file_info = FileInformation::new("./src/lib.rs", &mut 0);
+        record.apply_to_file_info(&mut file_info).unwrap();
+        let licenses = file_info
+            .license_information_in_file
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(licenses, vec!["Apache-2.0", "MIT"]);
+        assert_eq!(
+            file_info.comments_on_license.as_deref(),
+            Some("SPDX-License-Identifier: MIT OR Apache-2.0")
+        );
+    }
 }
@@ -0,0 +1,133 @@
+use std::collections::BTreeSet;
+
+use digest::{consts::U32, DynDigest};
+use spdx_rs::models::{Algorithm, Checksum};
+
+use crate::analyzers::FileAnalyzer;
+
+/// checksum algorithms, ordered from weakest to strongest
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChecksumAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    #[clap(name = "blake2b-256")]
+    Blake2b256,
+    Sha512,
+}
+
+/// always computed: SPDX requires SHA1 of files, Git LFS identifies objects by SHA256
+pub const REQUIRED_ALGORITHMS: [ChecksumAlgorithm; 2] =
+    [ChecksumAlgorithm::Sha1, ChecksumAlgorithm::Sha256];
+
+impl ChecksumAlgorithm {
+    fn spdx_algorithm(self) -> Algorithm {
+        match self {
+            ChecksumAlgorithm::Md5 => Algorithm::MD5,
+            ChecksumAlgorithm::Sha1 => Algorithm::SHA1,
+            ChecksumAlgorithm::Sha256 => Algorithm::SHA256,
+            ChecksumAlgorithm::Blake2b256 => Algorithm::BLAKE2B256,
+            ChecksumAlgorithm::Sha512 => Algorithm::SHA512,
+        }
+    }
+
+    fn hasher(self) -> Box<dyn DynDigest + Send> {
+        match self {
+            ChecksumAlgorithm::Md5 => Box::new(md5::Md5::default()),
+            ChecksumAlgorithm::Sha1 => Box::new(::sha1::Sha1::default()),
+            ChecksumAlgorithm::Sha256 => Box::new(sha2::Sha256::default()),
+            ChecksumAlgorithm::Blake2b256 => Box::new(blake2::Blake2b::<U32>::default()),
+            ChecksumAlgorithm::Sha512 => Box::new(sha2::Sha512::default()),
+        }
+    }
+}
+
+/// computes checksums of all given algorithms in one pass
+pub struct ChecksumWriter {
+    hashers: Vec<(ChecksumAlgorithm, Box<dyn DynDigest + Send>)>,
+}
+
+impl ChecksumWriter {
+    /// `REQUIRED_ALGORITHMS` are added to `algorithms`
+    pub fn new(algorithms: &[ChecksumAlgorithm]) -> Self {
+        let algorithms = algorithms
+            .iter()
+            .chain(REQUIRED_ALGORITHMS.iter())
+            .copied()
+            .collect::<BTreeSet<_>>();
+        Self {
+            hashers: algorithms
+                .into_iter()
+                .map(|algorithm| (algorithm, algorithm.hasher()))
+                .collect(),
+        }
+    }
+}
+
+impl std::io::Write for ChecksumWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for (_, hasher) in &mut self.hashers {
+            hasher.update(buf);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl FileAnalyzer for ChecksumWriter {
+    /// weakest first
+    type Output = Vec<(ChecksumAlgorithm, Checksum)>;
+
+    fn finish(self) -> Self::Output {
+        self.hashers
+            .into_iter()
+            .map(|(algorithm, hasher)| {
+                let hash = base16ct::lower::encode_string(&hasher.finalize());
+                (algorithm, Checksum::new(algorithm.spdx_algorithm(), &hash))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use crate::analyzers::{
+        checksum::{ChecksumAlgorithm, ChecksumWriter},
+        FileAnalyzer,
+    };
+
+    #[test]
+    fn test_checksums() {
+        let mut writer = ChecksumWriter::new(&[ChecksumAlgorithm::Md5, ChecksumAlgorithm::Sha512]);
+        writer.write_all(b"abc").unwrap();
+        let checksums = writer.finish();
+        let values = checksums
+            .iter()
+            .map(|(algorithm, checksum)| (*algorithm, checksum.value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                (ChecksumAlgorithm::Md5, "900150983cd24fb0d6963f7d28e17f72"),
+                (
+                    ChecksumAlgorithm::Sha1,
+                    "a9993e364706816aba3e25717850c26c9cd0d89d"
+                ),
+                (
+                    ChecksumAlgorithm::Sha256,
+                    "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+                ),
+                (
+                    ChecksumAlgorithm::Sha512,
+                    "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+                     2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+                ),
+            ]
+        );
+    }
+}
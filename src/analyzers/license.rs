@@ -1,64 +1,219 @@
 // SPDX-License-Identifier: MIT
 
-use spdx_rs::models::SimpleExpression;
-use std::io::{BufRead, BufReader, Cursor};
+use spdx_rs::models::{SpdxExpression, SpdxExpressionError};
 
-use crate::analyzers::{FileAnalyzer, SpdxFileAnalyzeError};
+use crate::analyzers::{
+    FileAnalyzer, FileLicense, RecordAnalyzer, SpdxFileAnalyzeError, SpdxFileAnalyzeSuccess,
+};
 
 pub struct SourceLicenceAnalyzer {
-    buffer: Cursor<Vec<u8>>,
+    buffer: Vec<u8>,
+    /// detected once head of file is buffered
+    encoding: Option<Encoding>,
 }
 
 impl SourceLicenceAnalyzer {
     pub fn new() -> Self {
         Self {
-            buffer: Cursor::new(Vec::new()),
+            buffer: Vec::new(),
+            encoding: None,
         }
     }
 }
 
 impl std::io::Write for SourceLicenceAnalyzer {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.buffer.write(buf)
+        // binary file has no license tag, so rest of it is not buffered
+        if self.encoding == Some(Encoding::Binary) {
+            return Ok(buf.len());
+        }
+        self.buffer.extend_from_slice(buf);
+        if self.encoding.is_none() && self.buffer.len() >= BINARY_CHECK_SIZE {
+            let encoding = detect_encoding(&self.buffer);
+            if encoding == Encoding::Binary {
+                self.buffer = Vec::new();
+            }
+            self.encoding = Some(encoding);
+        }
+        Ok(buf.len())
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
-        self.buffer.flush()
+        Ok(())
     }
 }
 
-const TARGET: &str = "SPDX-License-Identifier:";
+/// license tag, as in SPDX license comment of the file too
+pub const TARGET: &str = "SPDX-License-Identifier:";
+
+/// comment leaders which may precede license tag, longer one first
+///
+/// `"""` and `'''` start Python docstring, `"` starts Vim script comment,
+/// `REM` and `::` start batch file comment and `..` starts reStructuredText comment.
+const COMMENT_LEADERS: &[&str] = &[
+    "<!--", "\"\"\"", "'''", "//", "/*", "(*", "{-", "--", "..", "::", "REM", "rem", "#", ";", "%",
+    "*", "!", "\"",
+];
+
+/// endings of block comments, not part of license expression
+const COMMENT_ENDS: &[&str] = &["*/", "-->", "*)", "-}", "\"\"\"", "'''"];
+
+/// whether text before license tag is comment leader, repeated or followed by
+/// doc comment marker as `///`, `//!`, `/**` and `##`
+fn is_comment_leader(prefix: &str) -> bool {
+    prefix.is_empty()
+        || COMMENT_LEADERS.iter().any(|leader| {
+            prefix.strip_prefix(leader).is_some_and(|rest| {
+                rest.chars()
+                    .all(|c| leader.contains(c) || c == '!' || c == '*')
+            })
+        })
+}
+
+/// license tag found in file
+#[derive(Debug)]
+pub enum LicenseTag {
+    Expression(SpdxExpression),
+    /// tag whose value is not valid license expression
+    Malformed {
+        expression: String,
+        error: SpdxExpressionError,
+    },
+}
+
+/// license expression of tag in comment, `None` if line has no tag or
+/// tag is not in comment, e.g. in string literal
+fn find_license_tag(line: &str) -> Option<&str> {
+    let start = line.find(TARGET)?;
+    if !is_comment_leader(line[..start].trim()) {
+        return None;
+    }
+    let mut expression = line[start + TARGET.len()..].trim();
+    for end in COMMENT_ENDS {
+        if let Some(stripped) = expression.strip_suffix(end) {
+            expression = stripped.trim_end();
+        }
+    }
+    Some(expression)
+}
 
-fn is_whitespace(line: &str, index: usize) -> bool {
-    let line_bytes = line.as_bytes();
-    match line_bytes[index] {
-        b' ' | b'\t' => true,
-        _ => false,
+fn parse_license_tag(expression: &str) -> LicenseTag {
+    match SpdxExpression::parse(expression) {
+        Ok(expression) => LicenseTag::Expression(expression),
+        Err(error) => LicenseTag::Malformed {
+            expression: expression.to_string(),
+            error,
+        },
     }
 }
 
-fn parse_license_identifer(line: &str) -> Result<SimpleExpression, SpdxFileAnalyzeError> {
-    let mut start = line.find(TARGET).unwrap() + TARGET.len();
-    while is_whitespace(line, start) {
-        start = start + 1
+/// files with NUL in first bytes are binary, as git detects them
+const BINARY_CHECK_SIZE: usize = 8000;
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+const UTF16LE_BOM: &[u8] = b"\xff\xfe";
+const UTF16BE_BOM: &[u8] = b"\xfe\xff";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Binary,
+}
+
+/// encoding by BOM, or by NULs in head of file
+fn detect_encoding(buffered: &[u8]) -> Encoding {
+    if buffered.starts_with(UTF16LE_BOM) {
+        return Encoding::Utf16Le;
+    }
+    if buffered.starts_with(UTF16BE_BOM) {
+        return Encoding::Utf16Be;
+    }
+    let head = &buffered[..buffered.len().min(BINARY_CHECK_SIZE)];
+    if !head.contains(&0) {
+        return Encoding::Utf8;
     }
-    let remains = &line[start..];
-    Ok(SimpleExpression::parse(remains)?)
+    guess_utf16(head).unwrap_or(Encoding::Binary)
+}
+
+/// UTF-16 without BOM, as written by some Windows tools, has NUL in every
+/// other byte of ASCII text and no NUL in the other bytes
+fn guess_utf16(head: &[u8]) -> Option<Encoding> {
+    let units = head.len() / 2;
+    let even_nuls = head.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_nuls = head.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+    if odd_nuls * 2 > units && even_nuls == 0 {
+        Some(Encoding::Utf16Le)
+    } else if even_nuls * 2 > units && odd_nuls == 0 {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+fn decode_utf16(data: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units = data
+        .chunks_exact(2)
+        .map(|unit| from_bytes([unit[0], unit[1]]));
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+fn find_license_tag_in_text(text: &str) -> Option<LicenseTag> {
+    text.lines()
+        .find_map(find_license_tag)
+        .map(parse_license_tag)
 }
 
 impl FileAnalyzer for SourceLicenceAnalyzer {
-    type Output = Result<Option<SimpleExpression>, SpdxFileAnalyzeError>;
+    type Output = Option<LicenseTag>;
 
+    /// scan bytes of file, so files which are not valid UTF-8 (e.g. Latin-1) are scanned too
     fn finish(self) -> Self::Output {
-        let buffered = self.buffer.get_ref();
-        let lines = BufReader::new(Cursor::new(buffered)).lines();
-        for line in lines {
-            let line = line?;
-            if line.contains(TARGET) {
-                return Ok(Some(parse_license_identifer(&line)?));
+        let buffered = self.buffer.as_slice();
+        match self.encoding.unwrap_or_else(|| detect_encoding(buffered)) {
+            Encoding::Utf16Le => {
+                let data = buffered.strip_prefix(UTF16LE_BOM).unwrap_or(buffered);
+                find_license_tag_in_text(&decode_utf16(data, u16::from_le_bytes))
+            }
+            Encoding::Utf16Be => {
+                let data = buffered.strip_prefix(UTF16BE_BOM).unwrap_or(buffered);
+                find_license_tag_in_text(&decode_utf16(data, u16::from_be_bytes))
             }
+            Encoding::Utf8 => buffered
+                .strip_prefix(UTF8_BOM)
+                .unwrap_or(buffered)
+                .split(|b| *b == b'\n')
+                .filter(|line| line.windows(TARGET.len()).any(|w| w == TARGET.as_bytes()))
+                .find_map(|line| {
+                    find_license_tag(&String::from_utf8_lossy(line)).map(parse_license_tag)
+                }),
+            Encoding::Binary => None,
         }
-        Ok(None)
+    }
+}
+
+impl RecordAnalyzer for SourceLicenceAnalyzer {
+    fn contribute(
+        self: Box<Self>,
+        record: &mut SpdxFileAnalyzeSuccess,
+    ) -> Result<(), SpdxFileAnalyzeError> {
+        match FileAnalyzer::finish(*self) {
+            Some(LicenseTag::Expression(license)) => {
+                record.set_license_information_in_file(FileLicense::Expression(license));
+            }
+            Some(LicenseTag::Malformed { expression, error }) => {
+                record.set_license_information_in_file(FileLicense::NoAssertion);
+                record.push_warning(format!(
+                    "malformed license tag \"{}\": {}",
+                    expression, error
+                ));
+            }
+            None => {}
+        }
+        Ok(())
     }
 }
 
@@ -66,20 +221,168 @@ impl FileAnalyzer for SourceLicenceAnalyzer {
 mod tests {
     use std::io::Write;
 
-    use crate::analyzers::{license::SourceLicenceAnalyzer, FileAnalyzer};
+    use crate::analyzers::{
+        license::{
+            find_license_tag, Encoding, LicenseTag, SourceLicenceAnalyzer, BINARY_CHECK_SIZE,
+        },
+        AnalyzerRegistry, FileAnalyzer,
+    };
 
     #[test]
     fn test_find_license_identifier() {
         let mut analyzer = SourceLicenceAnalyzer::new();
         analyzer
-            .write(b"// SPDX-License-Identifier: GPL-3\n")
+            .write_all(b"// SPDX-License-Identifier: GPL-3\n")
             .unwrap();
-        analyzer.write(b"// more comment here").unwrap();
+        analyzer.write_all(b"// more comment here").unwrap();
         analyzer.flush().unwrap();
         let result = analyzer.finish();
         let result = result.unwrap(); // unwrap Option
-        let result = result.unwrap(); // unwrap Ok
-        assert_eq!(result.document_ref, None);
-        assert_eq!(result.identifier.as_str(), "GPL-3");
+        assert!(matches!(result, LicenseTag::Expression(e) if e.to_string() == "GPL-3"));
+    }
+
+    #[test]
+    fn test_find_license_expression() {
+        let mut analyzer = SourceLicenceAnalyzer::new();
+        analyzer
+            .write_all(b"// SPDX-License-Identifier: MIT OR Apache-2.0 WITH LLVM-exception \n")
+            .unwrap();
+        let result = match analyzer.finish().unwrap() {
+            LicenseTag::Expression(expression) => expression,
+            tag => panic!("unexpected {:?}", tag),
+        };
+        let mut licenses = result
+            .licenses()
+            .into_iter()
+            .map(|license| license.identifier.clone())
+            .collect::<Vec<_>>();
+        licenses.sort();
+        assert_eq!(licenses, vec!["Apache-2.0", "MIT"]);
+        assert_eq!(
+            result.exceptions().into_iter().collect::<Vec<_>>(),
+            vec!["LLVM-exception"]
+        );
+    }
+
+    #[test]
+    fn test_license_tag_in_comment() {
+        let tag = |prefix: &str, suffix: &str| {
+            find_license_tag(&format!("{}SPDX-License-Identifier: MIT{}", prefix, suffix))
+                .map(str::to_owned)
+        };
+        for (prefix, suffix) in [
+            ("", ""),
+            ("# ", ""),
+            ("## ", ""),
+            ("// ", ""),
+            ("/// ", ""),
+            ("//! ", ""),
+            (" * ", ""),
+            ("/* ", " */"),
+            ("-- ", ""),
+            (";; ", ""),
+            ("<!-- ", " -->"),
+            ("(* ", " *)"),
+            ("{- ", " -}"),
+            ("\"\"\"", "\"\"\""),
+            ("\" ", ""),
+            ("REM ", ""),
+            (":: ", ""),
+            (".. ", ""),
+        ] {
+            assert_eq!(tag(prefix, suffix).as_deref(), Some("MIT"), "{}", prefix);
+        }
+        // string literals and code, not comments
+        for prefix in [
+            "('",
+            "{",
+            ".",
+            "'",
+            "(\"",
+            "x = \"",
+            "const TARGET: &str = \"",
+        ] {
+            assert_eq!(tag(prefix, "\";"), None, "{}", prefix);
+        }
+    }
+
+    #[test]
+    fn test_malformed_license_tag() {
+        let mut analyzer = AnalyzerRegistry::default().file_analyzer();
+        analyzer
+            .write_all(b"// SPDX-License-Identifier: MIT OR\n")
+            .unwrap();
+        let record = analyzer.finish().unwrap();
+        assert_eq!(
+            record
+                .license_information_in_file()
+                .as_ref()
+                .unwrap()
+                .to_string(),
+            "NOASSERTION"
+        );
+        assert_eq!(record.warnings().len(), 1);
+        assert!(record.warnings()[0].starts_with("malformed license tag \"MIT OR\""));
+    }
+
+    #[test]
+    fn test_find_license_in_non_utf8() {
+        let mut analyzer = SourceLicenceAnalyzer::new();
+        analyzer
+            .write_all(b"# caf\xe9\n# SPDX-License-Identifier: MIT\n")
+            .unwrap();
+        assert!(matches!(analyzer.finish(), Some(LicenseTag::Expression(_))));
+
+        let mut analyzer = SourceLicenceAnalyzer::new();
+        let text = "// SPDX-License-Identifier: MIT\r\n";
+        analyzer.write_all(b"\xff\xfe").unwrap();
+        for unit in text.encode_utf16() {
+            analyzer.write_all(&unit.to_le_bytes()).unwrap();
+        }
+        assert!(
+            matches!(analyzer.finish(), Some(LicenseTag::Expression(e)) if e.to_string() == "MIT")
+        );
+
+        // binary file
+        let mut analyzer = SourceLicenceAnalyzer::new();
+        analyzer
+            .write_all(b"\x7fELF\x00\x00# SPDX-License-Identifier: MIT\n")
+            .unwrap();
+        assert!(analyzer.finish().is_none());
+    }
+
+    #[test]
+    fn test_find_license_in_utf16_without_bom() {
+        let text = "// SPDX-License-Identifier: MIT\n";
+        let mut analyzer = SourceLicenceAnalyzer::new();
+        for unit in text.encode_utf16() {
+            analyzer.write_all(&unit.to_le_bytes()).unwrap();
+        }
+        assert!(
+            matches!(analyzer.finish(), Some(LicenseTag::Expression(e)) if e.to_string() == "MIT")
+        );
+
+        let mut analyzer = SourceLicenceAnalyzer::new();
+        for unit in text.encode_utf16() {
+            analyzer.write_all(&unit.to_be_bytes()).unwrap();
+        }
+        assert!(
+            matches!(analyzer.finish(), Some(LicenseTag::Expression(e)) if e.to_string() == "MIT")
+        );
+    }
+
+    #[test]
+    fn test_binary_file_is_not_buffered() {
+        let mut analyzer = SourceLicenceAnalyzer::new();
+        let mut data = vec![0x7f, b'E', b'L', b'F', 0, 0, 0, 1];
+        data.resize(BINARY_CHECK_SIZE, 0xaa);
+        analyzer.write_all(&data).unwrap();
+        analyzer.write_all(&[0xaa; 65536]).unwrap();
+        analyzer
+            .write_all(b"\n# SPDX-License-Identifier: MIT\n")
+            .unwrap();
+        assert_eq!(analyzer.encoding, Some(Encoding::Binary));
+        assert!(analyzer.buffer.is_empty());
+        assert!(analyzer.finish().is_none());
     }
 }
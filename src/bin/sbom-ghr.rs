@@ -1,3 +1,5 @@
+use std::process::ExitCode;
+
 use anyhow::Result;
 use clap::Parser;
 use sbom_ghr::DescribeArgs;
@@ -15,7 +17,7 @@ enum Commands {
 }
 
 impl Commands {
-    pub async fn run(self) -> Result<()> {
+    pub async fn run(self) -> Result<ExitCode> {
         match self {
             Commands::Describe(a) => a.run().await,
         }
@@ -23,9 +25,7 @@ impl Commands {
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     let args = Args::parse();
-    args.command.run().await?;
-
-    Ok(())
+    args.command.run().await
 }
@@ -0,0 +1,470 @@
+//! CycloneDX 1.4 BOM emitter
+//!
+//! BOM is built from SPDX JSON representation of document. each SPDX package
+//! become a component, and files contained by the package become nested file
+//! components. files not contained by any package become top level components.
+
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
+
+use serde_json::{json, Value};
+use spdx_rs::models::SpdxExpression;
+
+use crate::analyzers::LICENSE_TAG;
+
+const SPEC_VERSION: &str = "1.4";
+
+pub struct Component {
+    bom_ref: String,
+    component_type: &'static str,
+    name: String,
+    version: Option<String>,
+    hashes: Vec<(&'static str, String)>,
+    licenses: Vec<String>,
+    /// compound license expression, written instead of `licenses`
+    license_expression: Option<String>,
+    components: Vec<Component>,
+}
+
+pub struct Bom {
+    timestamp: Option<String>,
+    components: Vec<Component>,
+}
+
+fn as_items(value: &Value) -> &[Value] {
+    match value {
+        Value::Array(items) => items,
+        _ => &[],
+    }
+}
+
+/// map SPDX checksum algorithm to CycloneDX hash algorithm
+fn hash_algorithm(spdx_algorithm: &str) -> Option<&'static str> {
+    match spdx_algorithm {
+        "MD5" => Some("MD5"),
+        "SHA1" => Some("SHA-1"),
+        "SHA256" => Some("SHA-256"),
+        "SHA384" => Some("SHA-384"),
+        "SHA512" => Some("SHA-512"),
+        "SHA3-256" => Some("SHA3-256"),
+        "SHA3-384" => Some("SHA3-384"),
+        "SHA3-512" => Some("SHA3-512"),
+        "BLAKE2b-256" => Some("BLAKE2b-256"),
+        "BLAKE2b-384" => Some("BLAKE2b-384"),
+        "BLAKE2b-512" => Some("BLAKE2b-512"),
+        "BLAKE3" => Some("BLAKE3"),
+        _ => None,
+    }
+}
+
+fn hashes(element: &Value) -> Vec<(&'static str, String)> {
+    as_items(&element["checksums"])
+        .iter()
+        .filter_map(|checksum| {
+            let algorithm = hash_algorithm(checksum["algorithm"].as_str()?)?;
+            let value = checksum["checksumValue"].as_str()?;
+            Some((algorithm, value.to_string()))
+        })
+        .collect()
+}
+
+fn licenses(element: &Value, key: &str) -> Vec<String> {
+    as_items(&element[key])
+        .iter()
+        .filter_map(Value::as_str)
+        .filter(|license| !matches!(*license, "NOASSERTION" | "NONE"))
+        .map(str::to_string)
+        .collect()
+}
+
+/// full license expression of file, kept in license comment as
+/// `SPDX-License-Identifier: <expression>`, `None` for single license or for
+/// comment which is not such expression
+fn license_expression(element: &Value) -> Option<String> {
+    let comment = element["licenseComments"]
+        .as_str()?
+        .strip_prefix(LICENSE_TAG)?;
+    let expression = SpdxExpression::parse(comment.trim()).ok()?;
+    let compound = expression.licenses().len() > 1 || !expression.exceptions().is_empty();
+    compound.then(|| expression.to_string())
+}
+
+fn element_id(element: &Value) -> &str {
+    element["SPDXID"].as_str().unwrap_or_default()
+}
+
+fn file_component(file: &Value, bom_ref: String) -> Component {
+    Component {
+        bom_ref,
+        component_type: "file",
+        name: file["fileName"].as_str().unwrap_or_default().to_string(),
+        version: None,
+        hashes: hashes(file),
+        licenses: licenses(file, "licenseInfoInFiles"),
+        license_expression: license_expression(file),
+        components: Vec::new(),
+    }
+}
+
+impl Bom {
+    pub fn from_spdx(document: &Value) -> Self {
+        let files = as_items(&document["files"]);
+        let relationships = as_items(&document["relationships"]);
+
+        let contained = relationships
+            .iter()
+            .filter(|r| r["relationshipType"].as_str() == Some("CONTAINS"))
+            .filter_map(|r| r["relatedSpdxElement"].as_str())
+            .collect::<HashSet<_>>();
+
+        let mut components = as_items(&document["packages"])
+            .iter()
+            .map(|package| {
+                let package_id = element_id(package);
+                let file_components = relationships
+                    .iter()
+                    .filter(|r| {
+                        r["relationshipType"].as_str() == Some("CONTAINS")
+                            && r["spdxElementId"].as_str() == Some(package_id)
+                    })
+                    .filter_map(|r| {
+                        let file_id = r["relatedSpdxElement"].as_str()?;
+                        files.iter().find(|f| element_id(f) == file_id)
+                    })
+                    .map(|file| {
+                        file_component(file, format!("{}:{}", package_id, element_id(file)))
+                    })
+                    .collect();
+                Component {
+                    bom_ref: package_id.to_string(),
+                    component_type: "application",
+                    name: package["name"].as_str().unwrap_or_default().to_string(),
+                    version: package["versionInfo"].as_str().map(str::to_string),
+                    hashes: hashes(package),
+                    licenses: licenses(package, "licenseInfoFromFiles"),
+                    license_expression: None,
+                    components: file_components,
+                }
+            })
+            .collect::<Vec<_>>();
+        components.extend(
+            files
+                .iter()
+                .filter(|file| !contained.contains(element_id(file)))
+                .map(|file| file_component(file, element_id(file).to_string())),
+        );
+
+        Self {
+            timestamp: document["creationInfo"]["created"]
+                .as_str()
+                .map(str::to_string),
+            components,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut metadata = json!({
+            "tools": [{
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            }],
+        });
+        if let Some(timestamp) = &self.timestamp {
+            metadata["timestamp"] = json!(timestamp);
+        }
+        json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": SPEC_VERSION,
+            "version": 1,
+            "metadata": metadata,
+            "components": self.components.iter().map(Component::to_json).collect::<Vec<_>>(),
+        })
+    }
+
+    /// write BOM as CycloneDX JSON
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut writer, &self.to_json())?;
+        writeln!(writer)?;
+        writer.flush()
+    }
+
+    /// write BOM as CycloneDX XML
+    pub fn write_xml<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<bom xmlns="http://cyclonedx.org/schema/bom/{}" version="1">"#,
+            SPEC_VERSION
+        )?;
+        writeln!(writer, "  <metadata>")?;
+        if let Some(timestamp) = &self.timestamp {
+            writeln!(writer, "    <timestamp>{}</timestamp>", escape(timestamp))?;
+        }
+        writeln!(writer, "    <tools>")?;
+        writeln!(writer, "      <tool>")?;
+        writeln!(writer, "        <name>{}</name>", env!("CARGO_PKG_NAME"))?;
+        writeln!(
+            writer,
+            "        <version>{}</version>",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        writeln!(writer, "      </tool>")?;
+        writeln!(writer, "    </tools>")?;
+        writeln!(writer, "  </metadata>")?;
+        write_xml_components(&mut writer, &self.components, 1)?;
+        writeln!(writer, "</bom>")?;
+        writer.flush()
+    }
+}
+
+impl Component {
+    fn to_json(&self) -> Value {
+        let mut component = json!({
+            "type": self.component_type,
+            "bom-ref": self.bom_ref,
+            "name": self.name,
+        });
+        if let Some(version) = &self.version {
+            component["version"] = json!(version);
+        }
+        if !self.hashes.is_empty() {
+            component["hashes"] = self
+                .hashes
+                .iter()
+                .map(|(alg, content)| json!({ "alg": alg, "content": content }))
+                .collect();
+        }
+        if let Some(expression) = &self.license_expression {
+            component["licenses"] = json!([{ "expression": expression }]);
+        } else if !self.licenses.is_empty() {
+            component["licenses"] = self
+                .licenses
+                .iter()
+                .map(|license| json!({ "license": license_json(license) }))
+                .collect();
+        }
+        if !self.components.is_empty() {
+            component["components"] = self.components.iter().map(Component::to_json).collect();
+        }
+        component
+    }
+
+    fn write_xml<W: Write>(&self, writer: &mut W, depth: usize) -> io::Result<()> {
+        let indent = "  ".repeat(depth);
+        writeln!(
+            writer,
+            r#"{}<component type="{}" bom-ref="{}">"#,
+            indent,
+            self.component_type,
+            escape(&self.bom_ref)
+        )?;
+        writeln!(writer, "{}  <name>{}</name>", indent, escape(&self.name))?;
+        if let Some(version) = &self.version {
+            writeln!(writer, "{}  <version>{}</version>", indent, escape(version))?;
+        }
+        if !self.hashes.is_empty() {
+            writeln!(writer, "{}  <hashes>", indent)?;
+            for (alg, content) in &self.hashes {
+                writeln!(
+                    writer,
+                    r#"{}    <hash alg="{}">{}</hash>"#,
+                    indent,
+                    alg,
+                    escape(content)
+                )?;
+            }
+            writeln!(writer, "{}  </hashes>", indent)?;
+        }
+        if let Some(expression) = &self.license_expression {
+            writeln!(writer, "{}  <licenses>", indent)?;
+            writeln!(
+                writer,
+                "{}    <expression>{}</expression>",
+                indent,
+                escape(expression)
+            )?;
+            writeln!(writer, "{}  </licenses>", indent)?;
+        } else if !self.licenses.is_empty() {
+            writeln!(writer, "{}  <licenses>", indent)?;
+            for license in &self.licenses {
+                let element = if is_license_id(license) { "id" } else { "name" };
+                writeln!(
+                    writer,
+                    "{}    <license><{}>{}</{}></license>",
+                    indent,
+                    element,
+                    escape(license),
+                    element
+                )?;
+            }
+            writeln!(writer, "{}  </licenses>", indent)?;
+        }
+        write_xml_components(writer, &self.components, depth + 1)?;
+        writeln!(writer, "{}</component>", indent)
+    }
+}
+
+fn write_xml_components<W: Write>(
+    writer: &mut W,
+    components: &[Component],
+    depth: usize,
+) -> io::Result<()> {
+    if components.is_empty() {
+        return Ok(());
+    }
+    let indent = "  ".repeat(depth);
+    writeln!(writer, "{}<components>", indent)?;
+    for component in components {
+        component.write_xml(writer, depth + 1)?;
+    }
+    writeln!(writer, "{}</components>", indent)
+}
+
+/// only ids of SPDX license list are valid as CycloneDX license id, others
+/// (LicenseRef-*, unknown ids) are written as license name
+fn is_license_id(license: &str) -> bool {
+    ::spdx::license_id(license).is_some()
+}
+
+fn license_json(license: &str) -> Value {
+    if is_license_id(license) {
+        json!({ "id": license })
+    } else {
+        json!({ "name": license })
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::cyclonedx::{license_expression, Bom};
+
+    fn golden_bom() -> Bom {
+        let document: Value =
+            serde_json::from_str(include_str!("spdx/testdata/document.spdx.json")).unwrap();
+        Bom::from_spdx(&document)
+    }
+
+    #[test]
+    fn test_json_components() {
+        let bom = golden_bom().to_json();
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        assert_eq!(bom["specVersion"], "1.4");
+        assert_eq!(bom["metadata"]["timestamp"], "2022-07-01T00:00:00Z");
+
+        let components = bom["components"].as_array().unwrap();
+        assert_eq!(components.len(), 3);
+        assert_eq!(components[0]["name"], "git");
+        let files = components[0]["components"].as_array().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0]["type"], "file");
+        assert_eq!(files[0]["name"], "./src/lib.rs");
+        assert_eq!(files[0]["bom-ref"], "SPDXRef-1:SPDXRef-4");
+        assert_eq!(files[0]["hashes"][0]["alg"], "SHA-1");
+        assert_eq!(
+            files[0]["hashes"][0]["content"],
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(files[0]["licenses"][0]["license"]["id"], "MIT");
+
+        assert_eq!(components[1]["name"], "zip");
+        assert_eq!(components[1]["hashes"][0]["alg"], "SHA-1");
+
+        // not contained by any package
+        assert_eq!(components[2]["type"], "file");
+        assert_eq!(components[2]["name"], "./README.md");
+        assert_eq!(components[2]["bom-ref"], "SPDXRef-5");
+    }
+
+    #[test]
+    fn test_license_not_in_spdx_list() {
+        let document = json!({
+            "files": [{
+                "fileName": "./src/main.rs",
+                "SPDXID": "SPDXRef-1",
+                "licenseInfoInFiles": ["Apache-2.0", "LicenseRef-Custom", "Not-A-License"],
+            }],
+        });
+        let bom = Bom::from_spdx(&document);
+        let licenses = &bom.to_json()["components"][0]["licenses"];
+        assert_eq!(licenses[0]["license"]["id"], "Apache-2.0");
+        assert_eq!(licenses[1]["license"]["name"], "LicenseRef-Custom");
+        assert_eq!(licenses[2]["license"]["name"], "Not-A-License");
+
+        let mut output = Vec::new();
+        bom.write_xml(&mut output).unwrap();
+        let xml = String::from_utf8(output).unwrap();
+        assert!(xml.contains("<license><id>Apache-2.0</id></license>"));
+        assert!(xml.contains("<license><name>Not-A-License</name></license>"));
+    }
+
+    #[test]
+    fn test_xml() {
+        let mut output = Vec::new();
+        golden_bom().write_xml(&mut output).unwrap();
+        let xml = String::from_utf8(output).unwrap();
+        assert!(xml.contains(r#"<bom xmlns="http://cyclonedx.org/schema/bom/1.4" version="1">"#));
+        assert!(xml.contains(r#"<component type="file" bom-ref="SPDXRef-1:SPDXRef-3">"#));
+        assert!(
+            xml.contains(r#"<hash alg="SHA-1">a9993e364706816aba3e25717850c26c9cd0d89d</hash>"#)
+        );
+        assert!(xml.contains("<license><id>MIT</id></license>"));
+        assert!(xml.trim_end().ends_with("</bom>"));
+    }
+
+    #[test]
+    fn test_license_expression() {
+        let document = json!({
+            "files": [{
+                "fileName": "./src/lib.rs",
+                "SPDXID": "SPDXRef-1",
+                "licenseInfoInFiles": ["Apache-2.0", "MIT"],
+                "licenseComments": "SPDX-License-Identifier: MIT OR Apache-2.0",
+            }],
+        });
+        let bom = Bom::from_spdx(&document);
+        let licenses = &bom.to_json()["components"][0]["licenses"];
+        assert_eq!(licenses, &json!([{ "expression": "MIT OR Apache-2.0" }]));
+
+        let mut output = Vec::new();
+        bom.write_xml(&mut output).unwrap();
+        let xml = String::from_utf8(output).unwrap();
+        assert!(xml.contains("<expression>MIT OR Apache-2.0</expression>"));
+        assert!(!xml.contains("<license>"));
+    }
+
+    #[test]
+    fn test_license_expression_from_comment_only() {
+        let file = |comment: &str| json!({ "licenseComments": comment });
+        assert_eq!(
+            license_expression(&file(
+                "SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception"
+            ))
+            .as_deref(),
+            Some("Apache-2.0 WITH LLVM-exception")
+        );
+        assert_eq!(
+            license_expression(&file("SPDX-License-Identifier: MIT")),
+            None
+        );
+        assert_eq!(
+            license_expression(&file("SPDX-License-Identifier: MIT, see LICENSE file")),
+            None
+        );
+        assert_eq!(
+            license_expression(&file("dual licensed MIT OR Apache-2.0")),
+            None
+        );
+    }
+}
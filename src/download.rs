@@ -0,0 +1,392 @@
+//! download of archives and assets
+//!
+//! response is streamed into file. transient failures (connection errors,
+//! timeouts, 5xx) are retried with exponential backoff, and retry resumes
+//! from received bytes by `Range` request when server supports it. resumed
+//! range is conditional on validator (`ETag` or `Last-Modified`) of the
+//! first response by `If-Range`, so bytes of changed content are never
+//! spliced into received ones.
+
+use std::{
+    fs::File,
+    io::{Seek, SeekFrom, Write},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::{
+    header::{
+        HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, CONTENT_RANGE, ETAG, IF_RANGE,
+        LAST_MODIFIED, RANGE,
+    },
+    StatusCode,
+};
+use url::Url;
+
+/// files smaller than this are downloaded without progress
+const PROGRESS_MIN_SIZE: u64 = 16 * 1024 * 1024;
+
+/// wait before first retry, doubled on each retry
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+
+/// prints progress of large download to stderr, every 10% or every
+/// `PROGRESS_MIN_SIZE` bytes if size is unknown
+struct DownloadProgress<'u> {
+    url: &'u Url,
+    total: Option<u64>,
+    received: u64,
+    reported: u64,
+}
+
+impl<'u> DownloadProgress<'u> {
+    fn new(url: &'u Url, total: Option<u64>, received: u64) -> Self {
+        Self {
+            url,
+            total,
+            received,
+            reported: 0,
+        }
+    }
+
+    fn advance(&mut self, len: u64) {
+        self.received += len;
+        match self.total {
+            Some(total) if total < PROGRESS_MIN_SIZE => {}
+            Some(total) => {
+                let percent = self.received * 100 / total;
+                if percent >= self.reported + 10 {
+                    self.reported = percent - percent % 10;
+                    eprintln!(
+                        "downloading {} : {}% ({}/{} bytes)",
+                        self.url, percent, self.received, total
+                    );
+                }
+            }
+            None => {
+                if self.received >= self.reported + PROGRESS_MIN_SIZE {
+                    self.reported = self.received - self.received % PROGRESS_MIN_SIZE;
+                    eprintln!("downloading {} : {} bytes", self.url, self.received);
+                }
+            }
+        }
+    }
+}
+
+/// failure of one download attempt
+enum AttemptError {
+    Retryable(anyhow::Error),
+    Fatal(anyhow::Error),
+}
+
+impl From<reqwest::Error> for AttemptError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() || e.is_connect() || e.is_request() || e.is_body() {
+            AttemptError::Retryable(e.into())
+        } else {
+            AttemptError::Fatal(e.into())
+        }
+    }
+}
+
+impl From<std::io::Error> for AttemptError {
+    fn from(e: std::io::Error) -> Self {
+        AttemptError::Fatal(e.into())
+    }
+}
+
+impl From<tokio::time::error::Elapsed> for AttemptError {
+    fn from(_: tokio::time::error::Elapsed) -> Self {
+        AttemptError::Retryable(anyhow!("timed out"))
+    }
+}
+
+/// received part of download, and validator of the response it came from
+#[derive(Default)]
+struct Received {
+    len: u64,
+    validator: Option<HeaderValue>,
+}
+
+/// strong validator usable in `If-Range`, weak `ETag` is not
+fn validator(headers: &HeaderMap) -> Option<HeaderValue> {
+    match headers.get(ETAG) {
+        Some(etag) if !etag.as_bytes().starts_with(b"W/") => Some(etag.clone()),
+        _ => headers.get(LAST_MODIFIED).cloned(),
+    }
+}
+
+/// first byte position of `Content-Range: bytes <first>-<last>/<length>`
+fn content_range_start(headers: &HeaderMap) -> Option<u64> {
+    let range = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    let (start, _) = range.strip_prefix("bytes ")?.split_once('-')?;
+    start.trim().parse().ok()
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+}
+
+/// http client for archives and assets, authenticated by token if given
+///
+/// reqwest drops `Authorization` on redirect to another host, so token is not
+/// sent to storage hosts which GitHub redirects downloads to.
+#[derive(Clone)]
+pub struct Downloader {
+    client: reqwest::Client,
+    /// timeout of connection and of waiting each chunk of response
+    timeout: Duration,
+    retries: u32,
+    /// wait before first retry, doubled on each retry
+    backoff: Duration,
+}
+
+impl Downloader {
+    pub fn new(token: Option<&str>, timeout: Duration, retries: u32) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = token {
+            let mut authorization = HeaderValue::from_str(&format!("token {}", token))?;
+            authorization.set_sensitive(true);
+            headers.insert(AUTHORIZATION, authorization);
+        }
+        let client = reqwest::Client::builder()
+            .user_agent(concat!(
+                env!("CARGO_PKG_NAME"),
+                "/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .default_headers(headers)
+            .connect_timeout(timeout)
+            .build()?;
+        Ok(Self {
+            client,
+            timeout,
+            retries,
+            backoff: BACKOFF_BASE,
+        })
+    }
+
+    /// download `url` into `file`, and rewind it
+    pub async fn download(&self, url: &Url, accept: Option<&str>, file: &mut File) -> Result<()> {
+        let mut received = Received::default();
+        let mut retried = 0;
+        loop {
+            match self.try_download(url, accept, file, &mut received).await {
+                Ok(()) => break,
+                Err(AttemptError::Retryable(e)) if retried < self.retries => {
+                    let delay = self.backoff * 2u32.pow(retried);
+                    retried += 1;
+                    eprintln!(
+                        "downloading {} failed: {}, retry {}/{} in {:?}",
+                        url, e, retried, self.retries, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(AttemptError::Retryable(e) | AttemptError::Fatal(e)) => {
+                    return Err(e).with_context(|| format!("downloading {}", url));
+                }
+            }
+        }
+        file.seek(SeekFrom::Start(0))?;
+        Ok(())
+    }
+
+    async fn try_download(
+        &self,
+        url: &Url,
+        accept: Option<&str>,
+        file: &mut File,
+        received: &mut Received,
+    ) -> Result<(), AttemptError> {
+        let mut request = self.client.get(url.as_str());
+        if let Some(accept) = accept {
+            request = request.header(ACCEPT, accept);
+        }
+        // without validator, changed content could not be detected, so start over
+        let resuming = match &received.validator {
+            Some(validator) if received.len > 0 => {
+                request = request
+                    .header(RANGE, format!("bytes={}-", received.len))
+                    .header(IF_RANGE, validator.clone());
+                true
+            }
+            _ => false,
+        };
+        let mut response = tokio::time::timeout(self.timeout, request.send()).await??;
+
+        let status = response.status();
+        if status == StatusCode::PARTIAL_CONTENT {
+            if !resuming || content_range_start(response.headers()) != Some(received.len) {
+                *received = Received::default();
+                return Err(AttemptError::Retryable(anyhow!(
+                    "unexpected range {:?}",
+                    response.headers().get(CONTENT_RANGE)
+                )));
+            }
+            // resume after already written bytes
+        } else if status.is_success() {
+            // range not requested, or content changed since received bytes
+            *received = Received {
+                len: 0,
+                validator: validator(response.headers()),
+            };
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+        } else if is_retryable_status(status) {
+            return Err(AttemptError::Retryable(anyhow!("http status {}", status)));
+        } else {
+            return Err(AttemptError::Fatal(anyhow!("http status {}", status)));
+        }
+
+        let total = response.content_length().map(|len| len + received.len);
+        let mut progress = DownloadProgress::new(url, total, received.len);
+        while let Some(chunk) = tokio::time::timeout(self.timeout, response.chunk()).await?? {
+            file.write_all(&chunk)?;
+            received.len += chunk.len() as u64;
+            progress.advance(chunk.len() as u64);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Read,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use reqwest::StatusCode;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+    use url::Url;
+
+    use crate::download::{is_retryable_status, Downloader};
+
+    /// http server answering n-th connection with n-th of `responses` and closing it,
+    /// request heads are recorded in lower case
+    async fn serve(responses: Vec<&'static str>) -> (Url, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/asset", listener.local_addr().unwrap())).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut head = Vec::new();
+                let mut buf = [0; 1024];
+                while !head.ends_with(b"\r\n\r\n") {
+                    let len = socket.read(&mut buf).await.unwrap();
+                    if len == 0 {
+                        break;
+                    }
+                    head.extend_from_slice(&buf[..len]);
+                }
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&head).to_lowercase());
+                // body shorter than content-length is cut by closing connection
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn downloader() -> Downloader {
+        let mut downloader = Downloader::new(None, Duration::from_secs(5), 3).unwrap();
+        downloader.backoff = Duration::from_millis(50);
+        downloader
+    }
+
+    async fn download(downloader: &Downloader, url: &Url) -> anyhow::Result<String> {
+        let mut file = tempfile::tempfile()?;
+        downloader.download(url, None, &mut file).await?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    #[test]
+    fn test_retryable_status() {
+        assert!(is_retryable_status(StatusCode::BAD_GATEWAY));
+        assert!(is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_retryable_status(StatusCode::NOT_FOUND));
+        assert!(!is_retryable_status(StatusCode::UNAUTHORIZED));
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+        ])
+        .await;
+        let started = Instant::now();
+        assert_eq!(download(&downloader(), &url).await.unwrap(), "hello");
+        // 50ms and 100ms
+        assert!(started.elapsed() >= Duration::from_millis(150));
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_of_fatal_status() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        assert!(download(&downloader(), &url).await.is_err());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_resume_with_if_range() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nETag: \"v1\"\r\n\
+             Connection: close\r\n\r\n01234",
+            "HTTP/1.1 206 Partial Content\r\nContent-Length: 5\r\n\
+             Content-Range: bytes 5-9/10\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n56789",
+        ])
+        .await;
+        assert_eq!(download(&downloader(), &url).await.unwrap(), "0123456789");
+        let requests = requests.lock().unwrap();
+        assert!(!requests[0].contains("range:"));
+        assert!(requests[1].contains("range: bytes=5-"));
+        assert!(requests[1].contains("if-range: \"v1\""));
+    }
+
+    #[tokio::test]
+    async fn test_unexpected_range_starts_over() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nETag: \"v1\"\r\n\
+             Connection: close\r\n\r\n01234",
+            // range not at received bytes
+            "HTTP/1.1 206 Partial Content\r\nContent-Length: 10\r\n\
+             Content-Range: bytes 0-9/10\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n0123456789",
+            "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nETag: \"v2\"\r\n\
+             Connection: close\r\n\r\nabcdefghij",
+        ])
+        .await;
+        assert_eq!(download(&downloader(), &url).await.unwrap(), "abcdefghij");
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(!requests[2].contains("range:"));
+    }
+
+    #[tokio::test]
+    async fn test_no_resume_without_validator() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nConnection: close\r\n\r\n01234",
+            "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nConnection: close\r\n\r\nabcdefghij",
+        ])
+        .await;
+        assert_eq!(download(&downloader(), &url).await.unwrap(), "abcdefghij");
+        assert!(!requests.lock().unwrap()[1].contains("range:"));
+    }
+}
@@ -1,42 +1,77 @@
 use std::{
-    collections::HashMap,
-    io::{Cursor, SeekFrom},
+    collections::{HashMap, HashSet},
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::PathBuf,
+    process::ExitCode,
+    time::Duration,
 };
 
-use anyhow::{anyhow, bail, Result};
-use octocrab::Octocrab;
+use anyhow::{bail, Result};
+use octocrab::{models::repos::Asset, Octocrab};
+use spdx_rs::models::{Algorithm, Checksum, RelationshipType};
 use url::Url;
 
 use crate::{
-    analyzers::SpdxFileAnalyzeSuccess,
-    packages::{GitPackage, TarPackage, ZipPackage},
+    analyzers::FileAnalyzer,
+    cyclonedx::Bom,
+    download::Downloader,
+    packages::{
+        GitFiles, GitPackage, GitSubmodule, LfsPointer, SignatureKeys, TarPackage, UseAnalyzers,
+        ZipPackage,
+    },
     spdx::SpdxDocument,
+    verify::{Archive, Finding, VerificationReport},
 };
 
 mod analyzers;
+mod cyclonedx;
+mod download;
 mod packages;
 mod spdx;
+mod verify;
 
-fn file_name_from_url(url: &Url) -> Result<String> {
-    let segments = url
-        .path_segments()
-        .ok_or_else(|| anyhow!("can not take to path segments from url {}", url))?;
-    let last_segment = segments
-        .last()
-        .ok_or_else(|| anyhow!("can not take a last segment from url : {}", url))?;
-    Ok(last_segment.to_string())
+pub use crate::analyzers::{
+    AnalyzerRegistry, ChecksumAlgorithm, FileLicense, RecordAnalyzer, SpdxFileAnalyzeError,
+    SpdxFileAnalyzeSuccess,
+};
+
+const OCTET_STREAM: &str = "application/octet-stream";
+
+/// octocrab joins relative routes to base url, so base url must end with `/`
+fn api_base_url(url: &Url) -> Url {
+    let mut url = url.clone();
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    url
+}
+
+/// print warnings of file analysis and record them as annotations of the file
+fn annotate_warnings(spdx: &mut SpdxDocument, file_id: &str, path: &str, warnings: &[String]) {
+    for warning in warnings {
+        eprintln!("warning: {}: {}", path, warning);
+        spdx.push_annotation(file_id, warning);
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub enum OutputFormat {
+    /// SPDX JSON
+    Json,
+    /// SPDX tag-value
+    TagValue,
+    /// CycloneDX 1.4 JSON
+    CyclonedxJson,
+    /// CycloneDX 1.4 XML
+    CyclonedxXml,
 }
 
-async fn download_file_from_url<W: std::io::Write + std::io::Seek>(
-    url: &Url,
-    file: &mut W,
-) -> Result<()> {
-    let http_client = reqwest::Client::default();
-    let response = http_client.get(url.as_str()).send().await?;
-    let mut content = Cursor::new(response.bytes().await?);
-    std::io::copy(&mut content, file)?;
-    file.seek(SeekFrom::Start(0))?;
-    Ok(())
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum GitAnalyzeMode {
+    /// analyze files in working tree checkout
+    Checkout,
+    /// analyze blobs in tree of the tag directly from object database
+    Tree,
 }
 
 #[derive(clap::Args, Debug)]
@@ -44,110 +79,532 @@ pub struct DescribeArgs {
     owner: String,
     repo: String,
     tag: String,
+    /// output file path for SBOM (default: stdout)
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+    /// output format of SBOM
+    #[clap(short, long, value_enum, default_value = "json")]
+    format: OutputFormat,
+    /// how files of git tag are analyzed
+    #[clap(long, value_enum, default_value = "checkout")]
+    git_mode: GitAnalyzeMode,
+    /// use existing local git repository instead of cloning
+    #[clap(long)]
+    local_repo: Option<PathBuf>,
+    /// do not access GitHub, describe only git tag in local repository
+    #[clap(long, requires = "local_repo")]
+    offline: bool,
+    /// also analyze files of git submodules
+    #[clap(long)]
+    submodules: bool,
+    /// GitHub token for private repositories and higher rate limit
+    #[clap(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// checksum algorithms of files and assets, SHA1 and SHA256 are always included
+    #[clap(
+        long = "checksum",
+        value_enum,
+        use_value_delimiter = true,
+        default_value = "sha1,sha256"
+    )]
+    checksums: Vec<ChecksumAlgorithm>,
+    /// disable file analyzer by name (available: license)
+    #[clap(long = "disable-analyzer")]
+    disabled_analyzers: Vec<String>,
+    /// timeout in seconds of connecting and of each read of downloads
+    #[clap(long, default_value = "60")]
+    timeout: u64,
+    /// how many times failed download is retried
+    #[clap(long, default_value = "3")]
+    retries: u32,
+    /// GitHub REST API url, `https://<host>/api/v3` for GitHub Enterprise Server
+    #[clap(long, env = "GITHUB_API_URL", default_value = "https://api.github.com")]
+    github_api_url: Url,
+    /// gpg keyring to verify signature of tag and commit
+    #[clap(long)]
+    gpg_keyring: Option<PathBuf>,
+    /// ssh allowed signers file to verify signature of tag and commit
+    #[clap(long)]
+    allowed_signers: Option<PathBuf>,
 }
 
 type Files = HashMap<String, SpdxFileAnalyzeSuccess>;
 
+/// kind of release asset, decided by file name
+enum AssetKind {
+    Zip,
+    TarGz,
+    Binary,
+}
+
+impl AssetKind {
+    fn from_name(name: &str) -> Self {
+        if name.ends_with(".zip") {
+            AssetKind::Zip
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            AssetKind::TarGz
+        } else {
+            AssetKind::Binary
+        }
+    }
+}
+
+/// where git repository of the tag comes from
+enum GitSource {
+    Remote(Url),
+    Local(PathBuf),
+}
+
+struct AnalyzedAsset {
+    name: String,
+    download_url: Url,
+    checksums: Vec<Checksum>,
+    files: Option<Files>,
+}
+
 impl DescribeArgs {
-    async fn analyze_tar(tar_url: &Url) -> Result<Files> {
+    async fn analyze_tar(
+        downloader: Downloader,
+        tar_url: &Url,
+        analyzers: AnalyzerRegistry,
+    ) -> Result<Files> {
         let mut file = tempfile::tempfile()?;
-        download_file_from_url(tar_url, &mut file).await?;
-        let files_from_tar = TarPackage::from_read(file).analyze_files()?;
+        downloader.download(tar_url, None, &mut file).await?;
+        let mut tar = TarPackage::from_read(file);
+        tar.strip_root_directory();
+        tar.use_analyzers(&analyzers);
+        let files_from_tar = tar.analyze_files()?;
         Ok(files_from_tar)
     }
 
-    async fn analyze_zip(tar_url: &Url) -> Result<Files> {
+    async fn analyze_zip(
+        downloader: Downloader,
+        tar_url: &Url,
+        analyzers: AnalyzerRegistry,
+    ) -> Result<Files> {
         let mut file = tempfile::tempfile()?;
-        download_file_from_url(tar_url, &mut file).await?;
-        let files_from_tar = ZipPackage::from_read(file)?.analyze_files()?;
-        Ok(files_from_tar)
+        downloader.download(tar_url, None, &mut file).await?;
+        let mut zip = ZipPackage::from_read(file)?;
+        zip.strip_root_directory();
+        zip.use_analyzers(&analyzers);
+        let files_from_zip = zip.analyze_files()?;
+        Ok(files_from_zip)
     }
 
-    fn analyze_git(clone_url: Url, tag: String) -> Result<Files> {
-        let package = GitPackage::checkout(&clone_url, &tag)?;
-        Ok(package.analyze_files()?)
+    /// download asset, through API when authenticated as browser url of
+    /// private repository does not accept token
+    async fn analyze_asset(
+        downloader: Downloader,
+        asset: Asset,
+        authenticated: bool,
+        analyzers: AnalyzerRegistry,
+    ) -> Result<AnalyzedAsset> {
+        let mut file = tempfile::tempfile()?;
+        if authenticated {
+            downloader
+                .download(&asset.url, Some(OCTET_STREAM), &mut file)
+                .await?;
+        } else {
+            downloader
+                .download(&asset.browser_download_url, None, &mut file)
+                .await?;
+        }
+        let mut checksum_writer = analyzers.checksum_writer();
+        std::io::copy(&mut file, &mut checksum_writer)?;
+        file.seek(SeekFrom::Start(0))?;
+        let files = match AssetKind::from_name(&asset.name) {
+            AssetKind::Zip => {
+                let mut zip = ZipPackage::from_read(file)?;
+                zip.use_analyzers(&analyzers);
+                Some(zip.analyze_files()?)
+            }
+            AssetKind::TarGz => {
+                let mut tar = TarPackage::from_read(file);
+                tar.use_analyzers(&analyzers);
+                Some(tar.analyze_files()?)
+            }
+            AssetKind::Binary => None,
+        };
+        Ok(AnalyzedAsset {
+            name: asset.name,
+            download_url: asset.browser_download_url,
+            checksums: checksum_writer
+                .finish()
+                .into_iter()
+                .map(|(_, checksum)| checksum)
+                .collect(),
+            files,
+        })
+    }
+
+    fn analyze_git(
+        source: GitSource,
+        tag: String,
+        mode: GitAnalyzeMode,
+        submodules: bool,
+        signature_keys: SignatureKeys,
+        token: Option<String>,
+        analyzers: AnalyzerRegistry,
+    ) -> Result<GitFiles> {
+        let mut package = match source {
+            GitSource::Remote(clone_url) => GitPackage::fetch(&clone_url, &tag, token.as_deref())?,
+            GitSource::Local(repo_path) => GitPackage::open(&repo_path, &tag)?,
+        };
+        if submodules {
+            package.analyze_submodules();
+        }
+        package.verify_signatures(signature_keys);
+        package.use_analyzers(&analyzers);
+        let git_files = match mode {
+            GitAnalyzeMode::Checkout => package.analyze_files()?,
+            GitAnalyzeMode::Tree => package.analyze_tree()?,
+        };
+        Ok(git_files)
     }
 
     fn combine_file_analyze_result(
         spdx: &mut SpdxDocument,
-        git_files: Files,
+        git_files: GitFiles,
         zip_files: Option<Files>,
         tar_files: Option<Files>,
-    ) -> Result<()> {
-        let git_package = spdx.new_package("git"); // TODO: name for git
-        let git_package_id = (&git_package.package_spdx_identifier).clone();
+    ) -> Result<(String, VerificationReport)> {
+        let mut report = VerificationReport::default();
+        let mut git_package = spdx.new_package("git"); // TODO: name for git
+        git_package.package_version = Some(git_files.commit_id.clone());
+        git_package.package_download_location = git_files.download_location.clone();
+        let git_package_id = git_package.package_spdx_identifier.clone();
         spdx.push_package(git_package);
-        let zip_package = spdx.new_package("zip"); // TODO: name for zip
-        let zip_package_id = (&zip_package.package_spdx_identifier).clone();
-        spdx.push_package(zip_package);
-        let tar_package = spdx.new_package("tar"); // TODO: name for tar
-        let tar_package_id = (&tar_package.package_spdx_identifier).clone();
-        spdx.push_package(tar_package);
-
-        for (path, file_analyzed) in git_files {
-            let sum_in_git = file_analyzed.sha1_checksum();
+        for signature in &git_files.signatures {
+            eprintln!("{}", signature);
+            spdx.push_annotation(&git_package_id, &signature.to_string());
+        }
+        let mut archives = Vec::new();
+        for (archive, archive_files) in [(Archive::Zip, &zip_files), (Archive::Tar, &tar_files)] {
+            if let Some(archive_files) = archive_files {
+                let archive_package = spdx.new_package(&archive.to_string());
+                let archive_package_id = archive_package.package_spdx_identifier.clone();
+                spdx.push_package(archive_package);
+                archives.push((archive, archive_files, archive_package_id));
+            }
+        }
+        let git_paths = git_files.files.keys().cloned().collect::<HashSet<_>>();
+
+        for submodule in git_files.submodules {
+            if !archives.is_empty() {
+                report.push_expected(format!(
+                    "submodule {} is not contained in archives",
+                    submodule.path
+                ));
+            }
+            if let Some(reason) = &submodule.not_analyzed {
+                report.push(Finding::SubmoduleNotAnalyzed {
+                    path: submodule.path.clone(),
+                    reason: reason.clone(),
+                });
+            }
+            Self::combine_submodule(spdx, submodule, &git_package_id)?;
+        }
+
+        for (path, file_analyzed) in git_files.files {
             let mut file_info = spdx.new_file(&path);
-            file_info.file_checksum.push(sum_in_git.clone());
-            if let Some(license) = file_analyzed.license_information_in_file() {
-                file_info.license_information_in_file.push(license.clone());
+            let lfs_pointer = git_files.lfs.get(&path);
+            // checksums are of the content as committed, the pointer of LFS file
+            file_info.file_checksum.extend(file_analyzed.checksums());
+            let mut comments = Vec::new();
+            if let Some(blob) = git_files.blobs.get(&path) {
+                comments.push(format!("git blob {} mode {:06o}", blob.id, blob.mode));
             }
-            let file_id = (&file_info.file_spdx_identifier).clone();
+            comments.extend(file_analyzed.comments().iter().cloned());
+            if !comments.is_empty() {
+                file_info.file_comment = Some(comments.join("\n"));
+            }
+            // archive content of export-subst file differs from tag
+            let expected = git_files.archive_files.get(&path).unwrap_or(&file_analyzed);
+            let verifiable = !git_files.unverifiable.contains(&path);
+            file_analyzed.apply_license_to_file_info(&mut file_info);
+            let file_id = file_info.file_spdx_identifier.clone();
             spdx.push_file(file_info);
             spdx.push_contains(&git_package_id, &file_id);
+            annotate_warnings(spdx, &file_id, &path, file_analyzed.warnings());
+            let lfs_object_id =
+                lfs_pointer.map(|pointer| Self::push_lfs_object(spdx, &path, pointer, &file_id));
+            if !verifiable && !archives.is_empty() {
+                spdx.push_annotation(
+                    &file_id,
+                    &format!(
+                        "{} is not verified against archives, \
+                         its export-subst placeholders can not be reproduced",
+                        path
+                    ),
+                );
+            }
 
-            if let Some(z) = &zip_files {
-                if let Some(zip_analyzed) = z.get(&path) {
-                    let sum_in_zip = zip_analyzed.sha1_checksum();
-                    if sum_in_git != sum_in_zip {
-                        bail!("{path} checksum not matched (git vs zip)");
-                    };
+            for (archive, archive_files, archive_package_id) in &archives {
+                if let Some(archive_analyzed) = archive_files.get(&path) {
+                    let (sum_in_git, sum_in_archive) =
+                        expected.strongest_common_checksum(archive_analyzed);
+                    // archive contains either the pointer or the LFS object
+                    let archived_object = lfs_pointer.is_some_and(|pointer| {
+                        archive_analyzed.sha256_checksum().value == pointer.oid
+                    });
+                    let matched = sum_in_git == sum_in_archive || archived_object;
+                    if !verifiable {
+                        report.push_expected(format!(
+                            "{} in {} archive is not verified, \
+                             its export-subst placeholders can not be reproduced",
+                            path, archive
+                        ));
+                    } else if !matched {
+                        let (git, archived) = match lfs_pointer {
+                            Some(pointer) => (
+                                format!("sha256:{}", pointer.oid),
+                                format!("sha256:{}", archive_analyzed.sha256_checksum().value),
+                            ),
+                            None => (sum_in_git.value.clone(), sum_in_archive.value.clone()),
+                        };
+                        report.push(Finding::ChecksumMismatch {
+                            path: path.clone(),
+                            archive: *archive,
+                            git,
+                            archived,
+                        });
+                    }
+                    match &lfs_object_id {
+                        Some(object_id) if archived_object => {
+                            spdx.push_contains(archive_package_id, object_id)
+                        }
+                        _ => spdx.push_contains(archive_package_id, &file_id),
+                    }
                 } else {
-                    bail!("{path} in git not contains in zip package");
+                    report.push(Finding::MissingInArchive {
+                        path: path.clone(),
+                        archive: *archive,
+                    });
                 }
-                spdx.push_contains(&zip_package_id, &file_id);
             }
-            if let Some(t) = &tar_files {
-                if let Some(tar_analyzed) = t.get(&path) {
-                    let sum_in_tar = tar_analyzed.sha1_checksum();
-                    if sum_in_git != sum_in_tar {
-                        bail!("{path} checksum not matched (git vs tar) ");
-                    };
-                } else {
-                    bail!("{path} in git not contains in tar package");
-                }
-                spdx.push_contains(&tar_package_id, &file_id);
+        }
+
+        // files injected into archive which does not exist in tag
+        for (archive, archive_files, archive_package_id) in &archives {
+            let mut extra_paths = archive_files
+                .keys()
+                .filter(|path| !git_paths.contains(*path))
+                .collect::<Vec<_>>();
+            extra_paths.sort();
+            for path in extra_paths {
+                let mut file_info = spdx.new_file(path);
+                file_info
+                    .file_checksum
+                    .extend(archive_files[path].checksums());
+                let file_id = file_info.file_spdx_identifier.clone();
+                spdx.push_file(file_info);
+                spdx.push_contains(archive_package_id, &file_id);
+                spdx.push_annotation(
+                    &file_id,
+                    &format!(
+                        "{} is contained in {} archive but not in git",
+                        path, archive
+                    ),
+                );
+                report.push(Finding::ExtraInArchive {
+                    path: path.clone(),
+                    archive: *archive,
+                });
             }
         }
+
+        Ok((git_package_id, report))
+    }
+
+    /// LFS object as file of its own, as checksums of the pointer file are of
+    /// the pointer, returns SPDX id of the object
+    fn push_lfs_object(
+        spdx: &mut SpdxDocument,
+        path: &str,
+        pointer: &LfsPointer,
+        pointer_file_id: &str,
+    ) -> String {
+        let mut object_info = spdx.new_file(path);
+        object_info
+            .file_checksum
+            .push(Checksum::new(Algorithm::SHA256, &pointer.oid));
+        object_info.file_comment = Some(format!("git lfs object of {} bytes", pointer.size));
+        let object_id = object_info.file_spdx_identifier.clone();
+        spdx.push_file(object_info);
+        spdx.push_relationship_with_comment(
+            pointer_file_id,
+            &object_id,
+            RelationshipType::Other,
+            "git lfs pointer to object",
+        );
+        object_id
+    }
+
+    fn combine_submodule(
+        spdx: &mut SpdxDocument,
+        submodule: GitSubmodule,
+        git_package_id: &str,
+    ) -> Result<()> {
+        let mut package = spdx.new_package(submodule.path.trim_start_matches("./"));
+        package.package_version = Some(submodule.commit_id);
+        package.package_download_location = submodule.download_location;
+        let package_id = package.package_spdx_identifier.clone();
+        spdx.push_package(package);
+        // submodule is part of source tree, but built from another repository
+        spdx.push_contains(git_package_id, &package_id);
+        spdx.push_relationship(git_package_id, &package_id, RelationshipType::DependsOn);
+        spdx.push_annotation(
+            &package_id,
+            &format!(
+                "git submodule at {}, GitHub archives do not contain submodules",
+                submodule.path
+            ),
+        );
+
+        let files = submodule.files.map(|files| files.files).unwrap_or_default();
+        for (path, file_analyzed) in files {
+            let path = format!("{}/{}", submodule.path, path.trim_start_matches("./"));
+            let mut file_info = spdx.new_file(&path);
+            let warnings = file_analyzed.warnings().to_vec();
+            file_analyzed.apply_to_file_info(&mut file_info)?;
+            let file_id = file_info.file_spdx_identifier.clone();
+            spdx.push_file(file_info);
+            spdx.push_contains(&package_id, &file_id);
+            annotate_warnings(spdx, &file_id, &path, &warnings);
+        }
+        Ok(())
+    }
+
+    fn combine_asset(
+        spdx: &mut SpdxDocument,
+        asset: AnalyzedAsset,
+        source_package_id: &str,
+    ) -> Result<()> {
+        let mut package = spdx.new_package(&asset.name);
+        package.package_file_name = Some(asset.name.clone());
+        package.package_download_location = asset.download_url.to_string();
+        package.package_checksum.extend(asset.checksums);
+        let package_id = package.package_spdx_identifier.clone();
+        spdx.push_package(package);
+        spdx.push_relationship(
+            &package_id,
+            source_package_id,
+            RelationshipType::GeneratedFrom,
+        );
+
+        for (path, file_analyzed) in asset.files.unwrap_or_default() {
+            let mut file_info = spdx.new_file(&path);
+            let warnings = file_analyzed.warnings().to_vec();
+            file_analyzed.apply_to_file_info(&mut file_info)?;
+            let file_id = file_info.file_spdx_identifier.clone();
+            spdx.push_file(file_info);
+            spdx.push_contains(&package_id, &file_id);
+            annotate_warnings(spdx, &file_id, &path, &warnings);
+        }
         Ok(())
     }
 
-    pub async fn run(self) -> Result<()> {
+    /// describe release, exit code 2 means discrepancies found between git and archives
+    pub async fn run(self) -> Result<ExitCode> {
+        self.run_with_analyzers(AnalyzerRegistry::default()).await
+    }
+
+    /// describe release with analyzers of `analyzers`, e.g. with analyzers registered
+    /// by library user. `--checksum` and `--disable-analyzer` are applied to it.
+    pub async fn run_with_analyzers(self, mut analyzers: AnalyzerRegistry) -> Result<ExitCode> {
+        analyzers.checksum_algorithms(&self.checksums);
+        for name in &self.disabled_analyzers {
+            if !analyzers.disable(name) {
+                bail!(
+                    "unknown analyzer {} (available: {})",
+                    name,
+                    analyzers.names().collect::<Vec<_>>().join(", ")
+                );
+            }
+        }
+
         let mut spdx_doc = SpdxDocument::new(&format!("{}_{}", self.repo, self.tag));
-        let octocrab = Octocrab::builder().build()?;
-        let repo_client = octocrab.repos(self.owner, self.repo);
-        let repo = repo_client.get().await?;
-        let release = repo_client.releases().get_by_tag(&self.tag).await?;
-        println!("procesing release : {:?}", release);
-        let git_analyze_task = {
-            let clone_url = repo.clone_url.unwrap().clone();
-            tokio::spawn(async move { Self::analyze_git(clone_url, self.tag) })
-        };
-        let tar_analyze_task = if let Some(ref tar_url) = release.tarball_url {
-            let tar_url = tar_url.clone();
-            Some(tokio::spawn(
-                async move { Self::analyze_tar(&tar_url).await },
-            ))
+        let (clone_url, release) = if self.offline {
+            (None, None)
         } else {
-            None
+            // clone, archive and asset urls are given by API of the same host
+            let mut builder = Octocrab::builder().base_url(api_base_url(&self.github_api_url))?;
+            if let Some(token) = &self.token {
+                builder = builder.personal_token(token.clone());
+            }
+            let octocrab = builder.build()?;
+            let repo_client = octocrab.repos(&self.owner, &self.repo);
+            let repo = repo_client.get().await?;
+            let release = repo_client.releases().get_by_tag(&self.tag).await?;
+            eprintln!("procesing release : {:?}", release);
+            (repo.clone_url, Some(release))
         };
-        let zip_analyze_task = if let Some(ref zip_url) = release.zipball_url {
-            let zip_url = zip_url.clone();
-            Some(tokio::spawn(
-                async move { Self::analyze_zip(&zip_url).await },
-            ))
-        } else {
-            None
+        let git_source = match (&self.local_repo, clone_url) {
+            (Some(repo_path), _) => GitSource::Local(repo_path.clone()),
+            (None, Some(clone_url)) => GitSource::Remote(clone_url),
+            (None, None) => bail!("clone url of {}/{} not available", self.owner, self.repo),
+        };
+        let git_analyze_task = {
+            let tag = self.tag.clone();
+            let git_mode = self.git_mode;
+            let submodules = self.submodules;
+            let signature_keys = SignatureKeys {
+                gpg_keyring: self.gpg_keyring.clone(),
+                allowed_signers: self.allowed_signers.clone(),
+            };
+            let token = self.token.clone();
+            let analyzers = analyzers.clone();
+            tokio::spawn(async move {
+                Self::analyze_git(
+                    git_source,
+                    tag,
+                    git_mode,
+                    submodules,
+                    signature_keys,
+                    token,
+                    analyzers,
+                )
+            })
         };
+        let downloader = Downloader::new(
+            self.token.as_deref(),
+            Duration::from_secs(self.timeout),
+            self.retries,
+        )?;
+        let tar_analyze_task = release
+            .as_ref()
+            .and_then(|release| release.tarball_url.clone())
+            .map(|tar_url| {
+                let downloader = downloader.clone();
+                let analyzers = analyzers.clone();
+                tokio::spawn(
+                    async move { Self::analyze_tar(downloader, &tar_url, analyzers).await },
+                )
+            });
+        let zip_analyze_task = release
+            .as_ref()
+            .and_then(|release| release.zipball_url.clone())
+            .map(|zip_url| {
+                let downloader = downloader.clone();
+                let analyzers = analyzers.clone();
+                tokio::spawn(
+                    async move { Self::analyze_zip(downloader, &zip_url, analyzers).await },
+                )
+            });
+        let asset_analyze_tasks = release
+            .map(|release| release.assets)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|asset| {
+                eprintln!("processing asset : {:?}", asset);
+                let downloader = downloader.clone();
+                let authenticated = self.token.is_some();
+                let analyzers = analyzers.clone();
+                tokio::spawn(async move {
+                    Self::analyze_asset(downloader, asset, authenticated, analyzers).await
+                })
+            })
+            .collect::<Vec<_>>();
 
         // wait all analyze tasks
         let git_result = git_analyze_task.await??;
@@ -162,21 +619,265 @@ impl DescribeArgs {
             None
         };
 
-        Self::combine_file_analyze_result(&mut spdx_doc, git_result, zip_result, tar_result)?;
+        let (git_package_id, report) =
+            Self::combine_file_analyze_result(&mut spdx_doc, git_result, zip_result, tar_result)?;
+        eprint!("{}", report);
 
-        for asset in release.assets {
-            println!("processing asset : {:?}", asset);
+        for asset_task in asset_analyze_tasks {
+            Self::combine_asset(&mut spdx_doc, asset_task.await??, &git_package_id)?;
         }
 
-        Ok(())
+        // writers flush at the end, so write error of buffered output is reported
+        let writer: Box<dyn Write> = match self.output {
+            Some(path) => Box::new(BufWriter::new(std::fs::File::create(path)?)),
+            None => Box::new(BufWriter::new(std::io::stdout().lock())),
+        };
+        match self.format {
+            OutputFormat::Json => spdx_doc.write_json(writer)?,
+            OutputFormat::TagValue => spdx_doc.write_tag_value(writer)?,
+            OutputFormat::CyclonedxJson => {
+                Bom::from_spdx(&spdx_doc.to_json_value()?).write_json(writer)?
+            }
+            OutputFormat::CyclonedxXml => {
+                Bom::from_spdx(&spdx_doc.to_json_value()?).write_xml(writer)?
+            }
+        }
+
+        if report.is_empty() {
+            Ok(ExitCode::SUCCESS)
+        } else {
+            Ok(ExitCode::from(2))
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        io::Write,
+    };
+
+    use serde_json::Value;
+    use spdx_rs::models::{Algorithm, Checksum};
+    use url::Url;
+
+    use crate::{
+        analyzers::{AnalyzerRegistry, FileAnalyzer, SpdxFileAnalyzeSuccess},
+        api_base_url,
+        packages::{GitFiles, LfsPointer},
+        spdx::SpdxDocument,
+        AnalyzedAsset, AssetKind, DescribeArgs,
+    };
+
+    fn analyze(content: &[u8]) -> SpdxFileAnalyzeSuccess {
+        let mut analyzer = AnalyzerRegistry::default().file_analyzer();
+        analyzer.write_all(content).unwrap();
+        analyzer.finish().unwrap()
+    }
+
     #[test]
     fn it_works() {
         let result = 2 + 2;
         assert_eq!(result, 4);
     }
+
+    #[test]
+    fn test_api_base_url() {
+        let ghes = Url::parse("https://github.example.com/api/v3").unwrap();
+        assert_eq!(
+            api_base_url(&ghes).as_str(),
+            "https://github.example.com/api/v3/"
+        );
+        let github = Url::parse("https://api.github.com").unwrap();
+        assert_eq!(api_base_url(&github).as_str(), "https://api.github.com/");
+    }
+
+    #[test]
+    fn test_asset_kind() {
+        assert!(matches!(
+            AssetKind::from_name("tool-v1.zip"),
+            AssetKind::Zip
+        ));
+        assert!(matches!(
+            AssetKind::from_name("tool-v1.tar.gz"),
+            AssetKind::TarGz
+        ));
+        assert!(matches!(
+            AssetKind::from_name("tool-v1.tgz"),
+            AssetKind::TarGz
+        ));
+        assert!(matches!(
+            AssetKind::from_name("tool-v1-x86_64.exe"),
+            AssetKind::Binary
+        ));
+        assert!(matches!(
+            AssetKind::from_name("tool-v1.tar.xz"),
+            AssetKind::Binary
+        ));
+    }
+
+    #[test]
+    fn test_combine_asset() {
+        let mut files = HashMap::new();
+        files.insert("./bin/tool".to_string(), analyze(b"tool"));
+        let download_url =
+            Url::parse("https://github.com/owner/repo/releases/download/v1/tool.zip").unwrap();
+        let asset = AnalyzedAsset {
+            name: "tool.zip".to_string(),
+            download_url: download_url.clone(),
+            checksums: vec![Checksum::new(
+                Algorithm::SHA1,
+                "0123456789abcdef0123456789abcdef01234567",
+            )],
+            files: Some(files),
+        };
+
+        let mut spdx = SpdxDocument::new("test");
+        DescribeArgs::combine_asset(&mut spdx, asset, "SPDXRef-source").unwrap();
+        let document = spdx.to_json_value().unwrap();
+
+        let package = &document["packages"][0];
+        assert_eq!(package["name"], "tool.zip");
+        assert_eq!(package["downloadLocation"], download_url.as_str());
+        let package_id = package["SPDXID"].as_str().unwrap();
+        let file = &document["files"][0];
+        assert_eq!(file["fileName"], "./bin/tool");
+        let file_id = file["SPDXID"].as_str().unwrap();
+
+        let relationships = document["relationships"].as_array().unwrap();
+        let has_relationship = |element: &str, related: &str, relationship_type: &str| {
+            relationships.iter().any(|r| {
+                r["spdxElementId"] == element
+                    && r["relatedSpdxElement"] == related
+                    && r["relationshipType"] == relationship_type
+            })
+        };
+        assert!(has_relationship(
+            package_id,
+            "SPDXRef-source",
+            "GENERATED_FROM"
+        ));
+        assert!(has_relationship(package_id, file_id, "CONTAINS"));
+    }
+
+    #[test]
+    fn test_lfs_object_is_recorded() {
+        // sha256 of "abc"
+        let oid = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let pointer = format!(
+            "version https://git-lfs.github.com/spec/v1\noid sha256:{}\nsize 3\n",
+            oid
+        );
+        let pointer_analyzed = analyze(pointer.as_bytes());
+        let pointer_checksums = pointer_analyzed.checksums().collect::<Vec<_>>();
+        let git_files = GitFiles {
+            files: HashMap::from([("./data.bin".to_string(), pointer_analyzed)]),
+            archive_files: HashMap::new(),
+            unverifiable: Default::default(),
+            lfs: HashMap::from([(
+                "./data.bin".to_string(),
+                LfsPointer {
+                    oid: oid.to_string(),
+                    size: 3,
+                },
+            )]),
+            blobs: HashMap::new(),
+            commit_id: "0123456789abcdef0123456789abcdef01234567".to_string(),
+            download_location: "NOASSERTION".to_string(),
+            submodules: Vec::new(),
+            signatures: Vec::new(),
+        };
+        // archive contains LFS object
+        let zip_files = HashMap::from([("./data.bin".to_string(), analyze(b"abc"))]);
+
+        let mut spdx = SpdxDocument::new("test");
+        let (_, report) =
+            DescribeArgs::combine_file_analyze_result(&mut spdx, git_files, Some(zip_files), None)
+                .unwrap();
+        assert!(report.is_empty());
+
+        let document = spdx.to_json_value().unwrap();
+        let files = document["files"].as_array().unwrap();
+        assert_eq!(files.len(), 2);
+        let pointer_file = &files[0];
+        assert_eq!(pointer_file["fileName"], "./data.bin");
+        let checksums = pointer_file["checksums"].as_array().unwrap();
+        assert_eq!(checksums.len(), pointer_checksums.len());
+        for (checksum, expected) in checksums.iter().zip(&pointer_checksums) {
+            assert_eq!(checksum["checksumValue"], expected.value.as_str());
+        }
+
+        let object_file = &files[1];
+        assert_eq!(object_file["fileName"], "./data.bin");
+        assert_eq!(object_file["checksums"][0]["algorithm"], "SHA256");
+        assert_eq!(object_file["checksums"][0]["checksumValue"], oid);
+        assert_eq!(object_file["comment"], "git lfs object of 3 bytes");
+
+        let relationships = document["relationships"].as_array().unwrap();
+        let has_relationship = |element: &Value, related: &Value, relationship_type: &str| {
+            relationships.iter().any(|r| {
+                r["spdxElementId"] == *element
+                    && r["relatedSpdxElement"] == *related
+                    && r["relationshipType"] == relationship_type
+            })
+        };
+        assert!(has_relationship(
+            &pointer_file["SPDXID"],
+            &object_file["SPDXID"],
+            "OTHER"
+        ));
+        // zip archive contains the object, not the pointer
+        let zip_package = &document["packages"][1];
+        assert_eq!(zip_package["name"], "zip");
+        assert!(has_relationship(
+            &zip_package["SPDXID"],
+            &object_file["SPDXID"],
+            "CONTAINS"
+        ));
+        assert!(!has_relationship(
+            &zip_package["SPDXID"],
+            &pointer_file["SPDXID"],
+            "CONTAINS"
+        ));
+    }
+
+    #[test]
+    fn test_unverifiable_file_is_reported() {
+        let git_files = GitFiles {
+            files: HashMap::from([("./version.txt".to_string(), analyze(b"$Format:%d$\n"))]),
+            archive_files: HashMap::new(),
+            unverifiable: HashSet::from(["./version.txt".to_string()]),
+            lfs: HashMap::new(),
+            blobs: HashMap::new(),
+            commit_id: "0123456789abcdef0123456789abcdef01234567".to_string(),
+            download_location: "NOASSERTION".to_string(),
+            submodules: Vec::new(),
+            signatures: Vec::new(),
+        };
+        let zip_files = HashMap::from([("./version.txt".to_string(), analyze(b"tampered\n"))]);
+
+        let mut spdx = SpdxDocument::new("test");
+        let (_, report) =
+            DescribeArgs::combine_file_analyze_result(&mut spdx, git_files, Some(zip_files), None)
+                .unwrap();
+        assert!(report.is_empty());
+        assert!(report
+            .to_string()
+            .contains("./version.txt in zip archive is not verified"));
+
+        let document = spdx.to_json_value().unwrap();
+        let file_id = document["files"][0]["SPDXID"].as_str().unwrap();
+        assert!(document["annotations"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(
+                |annotation| annotation["spdxIdentifierReference"] == file_id
+                    && annotation["comment"]
+                        .as_str()
+                        .unwrap()
+                        .contains("is not verified against archives")
+            ));
+    }
 }
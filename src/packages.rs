@@ -3,11 +3,11 @@ mod git;
 mod tar;
 mod zip;
 
-use std::path::StripPrefixError;
+use std::{collections::HashMap, path::StripPrefixError};
 
 use ::zip::result::ZipError;
 
-use crate::analyzers::SpdxFileAnalyzeError;
+use crate::analyzers::{AnalyzerRegistry, SpdxFileAnalyzeError};
 
 #[derive(Debug, thiserror::Error)]
 pub enum PackageAnalyzeError {
@@ -21,6 +21,8 @@ pub enum PackageAnalyzeError {
     Context(String, Box<PackageAnalyzeError>),
     #[error("{0}")]
     PathStripFailed(#[from] StripPrefixError),
+    #[error("git error {0}")]
+    Git(#[from] git2::Error),
 }
 
 impl PackageAnalyzeError {
@@ -29,7 +31,99 @@ impl PackageAnalyzeError {
     }
 }
 
+/// package which analyzes each file with analyzers of a registry
+pub trait UseAnalyzers {
+    fn analyzers_mut(&mut self) -> &mut AnalyzerRegistry;
+
+    /// analyzers applied to each file, instead of default ones
+    fn use_analyzers(&mut self, analyzers: &AnalyzerRegistry) {
+        *self.analyzers_mut() = analyzers.clone();
+    }
+}
+
+/// normalize archive entry paths to same form as `PathPackage` (`./path`)
+///
+/// with `strip_root`, a top level directory shared by all entries is removed,
+/// e.g. `owner-repo-<sha>/` prefix of GitHub tarball and zipball.
+fn normalize_paths<T>(files: HashMap<String, T>, strip_root: bool) -> HashMap<String, T> {
+    let trimmed = |path: &str| {
+        path.trim_start_matches("./")
+            .trim_start_matches('/')
+            .to_owned()
+    };
+    let common_root = if strip_root {
+        let mut roots = files.keys().map(|path| {
+            trimmed(path)
+                .split_once('/')
+                .map(|(root, _)| root.to_owned())
+        });
+        match roots.next() {
+            Some(Some(first)) if roots.all(|root| root.as_ref() == Some(&first)) => Some(first),
+            _ => None,
+        }
+    } else {
+        None
+    };
+
+    files
+        .into_iter()
+        .map(|(path, value)| {
+            let path = trimmed(&path);
+            let path = match &common_root {
+                Some(root) => path[root.len() + 1..].to_owned(),
+                None => path,
+            };
+            (format!("./{}", path), value)
+        })
+        .collect()
+}
+
 pub use self::tar::TarPackage;
 pub use self::zip::ZipPackage;
 pub use file_system::PathPackage;
-pub use git::GitPackage;
+pub use git::{GitFiles, GitPackage, GitSubmodule, LfsPointer, SignatureKeys};
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::packages::normalize_paths;
+
+    fn paths(files: &[&str], strip_root: bool) -> Vec<String> {
+        let files = files
+            .iter()
+            .map(|f| (f.to_string(), ()))
+            .collect::<HashMap<_, _>>();
+        let mut normalized = normalize_paths(files, strip_root)
+            .into_keys()
+            .collect::<Vec<_>>();
+        normalized.sort();
+        normalized
+    }
+
+    #[test]
+    fn test_strip_github_archive_root() {
+        assert_eq!(
+            paths(
+                &[
+                    "owner-repo-0123abc/Cargo.toml",
+                    "owner-repo-0123abc/src/lib.rs"
+                ],
+                true
+            ),
+            vec!["./Cargo.toml", "./src/lib.rs"]
+        );
+    }
+
+    #[test]
+    fn test_keep_paths_without_common_root() {
+        assert_eq!(
+            paths(&["bin/tool", "README.md"], true),
+            vec!["./README.md", "./bin/tool"]
+        );
+        assert_eq!(
+            paths(&["tool-v1/bin/tool", "tool-v1/README.md"], false),
+            vec!["./tool-v1/README.md", "./tool-v1/bin/tool"]
+        );
+    }
+}
@@ -1,17 +1,24 @@
 use std::{
     collections::HashMap,
-    fs::read_dir,
+    fs::{read_dir, read_link},
     path::{Path, PathBuf},
 };
 
 use crate::{
-    analyzers::{FileAnalyzer, SpdxFileAnalyzeSuccess, SpdxFileAnalyzer},
-    packages::PackageAnalyzeError,
+    analyzers::{AnalyzerRegistry, FileAnalyzer, SpdxFileAnalyzeSuccess},
+    packages::{PackageAnalyzeError, UseAnalyzers},
 };
 
 pub struct PathPackage {
     path: PathBuf,
     ignores: Vec<String>,
+    analyzers: AnalyzerRegistry,
+}
+
+impl UseAnalyzers for PathPackage {
+    fn analyzers_mut(&mut self) -> &mut AnalyzerRegistry {
+        &mut self.analyzers
+    }
 }
 
 impl PathPackage {
@@ -19,6 +26,7 @@ impl PathPackage {
         Self {
             path: PathBuf::from(path),
             ignores: Vec::new(),
+            analyzers: AnalyzerRegistry::default(),
         }
     }
 
@@ -31,7 +39,7 @@ impl PathPackage {
     }
 
     pub fn analyze_files(
-        mut self,
+        self,
     ) -> Result<HashMap<String, SpdxFileAnalyzeSuccess>, PackageAnalyzeError> {
         let mut files = HashMap::new();
         let mut stack = Vec::new();
@@ -42,15 +50,22 @@ impl PathPackage {
             let dir = read_dir(stack.pop().unwrap())?;
             for entry in dir {
                 let entry = entry?;
-                let file_path = entry.path().strip_prefix(&path_prefix)?.to_owned();
+                let file_path = entry.path().strip_prefix(path_prefix)?.to_owned();
                 if !self.is_ignore(file_path.as_path()) {
-                    if entry.metadata()?.is_dir() {
+                    // symlink is not followed, as git records it
+                    let file_type = entry.file_type()?;
+                    if file_type.is_dir() {
                         stack.push(entry.path().as_path().to_owned());
                         continue;
                     } else {
-                        let mut file_analyzer = SpdxFileAnalyzer::new();
-                        let mut file = std::fs::File::open(entry.path())?;
-                        std::io::copy(&mut file, &mut file_analyzer)?;
+                        let mut file_analyzer = self.analyzers.file_analyzer();
+                        if file_type.is_symlink() {
+                            let target = link_target(&entry.path())?;
+                            std::io::copy(&mut target.as_slice(), &mut file_analyzer)?;
+                        } else {
+                            let mut file = std::fs::File::open(entry.path())?;
+                            std::io::copy(&mut file, &mut file_analyzer)?;
+                        }
                         let analyze_result = file_analyzer.finish().map_err(|e| {
                             PackageAnalyzeError::with_context(
                                 &format!("analyzing file {:?}", file_path),
@@ -70,6 +85,20 @@ impl PathPackage {
     }
 }
 
+/// link target as content of symlink blob in git
+fn link_target(path: &Path) -> std::io::Result<Vec<u8>> {
+    let target = read_link(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Ok(target.as_os_str().as_bytes().to_vec())
+    }
+    #[cfg(not(unix))]
+    {
+        Ok(target.to_string_lossy().replace('\\', "/").into_bytes())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
@@ -82,21 +111,47 @@ mod tests {
     #[test]
     fn test_analyze_self() -> Result<(), PackageAnalyzeError> {
         let mut path_pkg = PathPackage::new(Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()));
-        path_pkg.append_ignore(&PathBuf::from("target").as_path());
-        path_pkg.append_ignore(&PathBuf::from("vendor").as_path());
-        path_pkg.append_ignore(&PathBuf::from(".git").as_path());
-        path_pkg.append_ignore(&PathBuf::from("Cargo.lock").as_path());
+        path_pkg.append_ignore(PathBuf::from("target").as_path());
+        path_pkg.append_ignore(PathBuf::from("vendor").as_path());
+        path_pkg.append_ignore(PathBuf::from(".git").as_path());
+        path_pkg.append_ignore(PathBuf::from("Cargo.lock").as_path());
 
         let files = path_pkg.analyze_files()?;
-        assert!(files.get("./Cargo.toml").is_some());
-        assert!(files.get("./src/packages/file_system.rs").is_some());
-        assert!(files.get("./Cargo.lock").is_none()); // check works ignore
+        assert!(files.contains_key("./Cargo.toml"));
+        assert!(files.contains_key("./src/packages/file_system.rs"));
+        assert!(!files.contains_key("./Cargo.lock")); // check works ignore
 
         let license_rs = files.get("./src/analyzers/license.rs");
         let license_rs = license_rs.unwrap();
         let exp = license_rs.license_information_in_file().as_ref().unwrap();
-        assert_eq!(exp.identifier, "MIT");
+        assert_eq!(exp.to_string(), "MIT");
 
         Ok(())
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_is_not_followed() {
+        use std::os::unix::fs::symlink;
+
+        use crate::analyzers::{AnalyzerRegistry, FileAnalyzer};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("README"), "readme").unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        symlink("README", dir.path().join("README.md")).unwrap();
+        symlink("docs", dir.path().join("doc")).unwrap();
+        symlink("missing", dir.path().join("dangling")).unwrap();
+
+        let files = PathPackage::new(dir.path()).analyze_files().unwrap();
+        let sha1 = |content: &[u8]| {
+            let mut analyzer = AnalyzerRegistry::default().file_analyzer();
+            std::io::copy(&mut &content[..], &mut analyzer).unwrap();
+            analyzer.finish().unwrap().sha1_checksum().value.clone()
+        };
+        assert_eq!(files.len(), 4);
+        assert_eq!(files["./README.md"].sha1_checksum().value, sha1(b"README"));
+        assert_eq!(files["./doc"].sha1_checksum().value, sha1(b"docs"));
+        assert_eq!(files["./dangling"].sha1_checksum().value, sha1(b"missing"));
+    }
 }
@@ -1,32 +1,1104 @@
-use std::{collections::HashMap, fs};
+mod attributes;
+mod lfs;
+mod signature;
+mod submodule;
+mod subst;
 
-use git2::build::RepoBuilder;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use git2::{
+    build::CheckoutBuilder, AutotagOption, Commit, Cred, CredentialType, FetchOptions, ObjectType,
+    Oid, RemoteCallbacks, Repository, TreeWalkMode, TreeWalkResult,
+};
 use tempfile::TempDir;
 use url::Url;
 
 use crate::{
-    analyzers::SpdxFileAnalyzeSuccess,
-    packages::{file_system::PathPackage, PackageAnalyzeError},
+    analyzers::{AnalyzerRegistry, FileAnalyzer, SpdxFileAnalyzeSuccess},
+    packages::{PackageAnalyzeError, PathPackage, UseAnalyzers},
+};
+
+use self::attributes::{AttrState, GitAttributes};
+
+pub use self::{
+    lfs::LfsPointer,
+    signature::{SignatureCheck, SignatureKeys},
 };
 
+const GIT_ATTRIBUTES: &str = ".gitattributes";
+const GIT_MODULES: &str = "./.gitmodules";
+
+/// `git` command which supports `GIT_CONFIG_COUNT`, used for shallow fetch
+const MIN_GIT_VERSION: (u32, u32) = (2, 31);
+/// refspecs fetched by libgit2 instead of commit id, as it can not fetch by id
+const ALL_REFSPECS: &[&str] = &["+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"];
+
+/// environment variable passing token to `TOKEN_CREDENTIAL_HELPER`
+const TOKEN_ENV: &str = "SBOM_GHR_GIT_TOKEN";
+/// credential helper answering token as GitHub accepts, `x-access-token` as user
+const TOKEN_CREDENTIAL_HELPER: &str =
+    "!f() { echo username=x-access-token; echo \"password=$SBOM_GHR_GIT_TOKEN\"; }; f";
+
 pub struct GitPackage {
-    checkout_dir: TempDir,
+    repo_path: PathBuf,
+    /// fetched from `remote_url`, so submodules are fetched from their url too
+    fetched: bool,
+    /// fetched repository, removed on drop
+    _fetch_dir: Option<TempDir>,
+    commit_id: Oid,
+    remote_url: Option<String>,
+    download_location: String,
+    analyze_submodules: bool,
+    /// annotated tag object, `None` for lightweight tag
+    tag_id: Option<Oid>,
+    signature_keys: SignatureKeys,
+    /// token used to fetch, also used for submodules on the same host
+    token: Option<String>,
+    analyzers: AnalyzerRegistry,
+}
+
+/// analyzed files of git package
+pub struct GitFiles {
+    /// files in tag, as same set of files as `git archive` produces
+    pub files: HashMap<String, SpdxFileAnalyzeSuccess>,
+    /// files which content in archive differs from tag by `export-subst`,
+    /// analyzed with substituted content
+    pub archive_files: HashMap<String, SpdxFileAnalyzeSuccess>,
+    /// `export-subst` files which substitution can not be reproduced
+    pub unverifiable: HashSet<String>,
+    /// Git LFS pointer files, archive may contain either pointer or object content
+    pub lfs: HashMap<String, LfsPointer>,
+    /// blob id and file mode in tree of the tag
+    pub blobs: HashMap<String, GitBlob>,
+    /// commit SHA which tag points to
+    pub commit_id: String,
+    /// SPDX download location of the commit
+    pub download_location: String,
+    /// submodules in tree of the tag, never contained in `git archive` output
+    pub submodules: Vec<GitSubmodule>,
+    /// signature state of the tag and the commit
+    pub signatures: Vec<SignatureCheck>,
+}
+
+/// submodule (gitlink entry) in tree of the tag
+pub struct GitSubmodule {
+    pub path: String,
+    /// commit SHA which gitlink points to
+    pub commit_id: String,
+    /// SPDX download location of the submodule commit
+    pub download_location: String,
+    /// analyzed files of the submodule commit, when submodules are analyzed
+    pub files: Option<GitFiles>,
+    /// why files are not analyzed although submodules are analyzed
+    pub not_analyzed: Option<String>,
+}
+
+/// tree entry of the file in tag
+pub struct GitBlob {
+    pub id: String,
+    pub mode: i32,
+}
+
+/// blob entries in tree of the commit, keyed by `./path`
+fn tree_blobs(commit: &Commit) -> Result<HashMap<String, (Oid, i32)>, PackageAnalyzeError> {
+    let mut blobs = HashMap::new();
+    commit.tree()?.walk(TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() == Some(ObjectType::Blob) {
+            if let Some(name) = entry.name() {
+                blobs.insert(
+                    format!("./{}{}", root, name),
+                    (entry.id(), entry.filemode()),
+                );
+            }
+        }
+        TreeWalkResult::Ok
+    })?;
+    Ok(blobs)
+}
+
+/// gitlink entries in tree of the commit, keyed by `./path`
+fn tree_submodules(commit: &Commit) -> Result<Vec<(String, Oid)>, PackageAnalyzeError> {
+    let mut submodules = Vec::new();
+    commit.tree()?.walk(TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() == Some(ObjectType::Commit) {
+            if let Some(name) = entry.name() {
+                submodules.push((format!("./{}{}", root, name), entry.id()));
+            }
+        }
+        TreeWalkResult::Ok
+    })?;
+    submodules.sort();
+    Ok(submodules)
+}
+
+/// load all `.gitattributes` in tree, shallower one first
+fn load_attributes(
+    repo: &Repository,
+    blobs: &HashMap<String, (Oid, i32)>,
+) -> Result<GitAttributes, PackageAnalyzeError> {
+    let mut attribute_files = blobs
+        .keys()
+        .map(|path| path.trim_start_matches("./"))
+        .filter(|path| path.rsplit('/').next() == Some(GIT_ATTRIBUTES))
+        .collect::<Vec<_>>();
+    attribute_files.sort_by_key(|path| path.matches('/').count());
+
+    let mut attributes = GitAttributes::default();
+    for path in attribute_files {
+        let (id, _) = blobs[&format!("./{}", path)];
+        let blob = repo.find_blob(id)?;
+        let dir = path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+        attributes.add_file(dir, &String::from_utf8_lossy(blob.content()));
+    }
+    Ok(attributes)
+}
+
+/// id of annotated tag object, `None` for lightweight tag
+fn tag_object_id(repo: &Repository, tag: &str) -> Option<Oid> {
+    let id = repo.refname_to_id(&format!("refs/tags/{}", tag)).ok()?;
+    repo.find_tag(id).ok().map(|tag| tag.id())
+}
+
+/// resolve annotated or lightweight tag to commit
+fn resolve_tag<'r>(repo: &'r Repository, tag: &str) -> Result<Commit<'r>, PackageAnalyzeError> {
+    let reference = repo
+        .find_reference(&format!("refs/tags/{}", tag))
+        .map_err(|e| {
+            PackageAnalyzeError::with_context(&format!("tag {} not found", tag), e.into())
+        })?;
+    Ok(reference.peel_to_commit()?)
+}
+
+/// major and minor version of `git --version` output, as `git version 2.39.2`
+fn parse_git_version(output: &str) -> Option<(u32, u32)> {
+    let version = output.trim().strip_prefix("git version ")?;
+    let mut numbers = version.split(|c: char| !c.is_ascii_digit());
+    Some((numbers.next()?.parse().ok()?, numbers.next()?.parse().ok()?))
+}
+
+/// version of `git` command, `None` if it is not installed
+fn git_version() -> Option<(u32, u32)> {
+    let output = Command::new("git")
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    parse_git_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+impl UseAnalyzers for GitPackage {
+    fn analyzers_mut(&mut self) -> &mut AnalyzerRegistry {
+        &mut self.analyzers
+    }
 }
 
 impl GitPackage {
-    pub fn checkout(clone_url: &Url, tag: &str) -> Result<Self, PackageAnalyzeError> {
+    /// package of the commit in local repository, with default options
+    fn new(repo_path: PathBuf, commit_id: Oid, remote_url: Option<String>) -> Self {
+        let download_location = match &remote_url {
+            Some(url) => format!("git+{}@{}", url, commit_id),
+            None => "NOASSERTION".to_string(),
+        };
+        Self {
+            repo_path,
+            fetched: false,
+            _fetch_dir: None,
+            commit_id,
+            remote_url,
+            download_location,
+            analyze_submodules: false,
+            tag_id: None,
+            signature_keys: SignatureKeys::default(),
+            token: None,
+            analyzers: AnalyzerRegistry::default(),
+        }
+    }
+
+    /// package of the commit fetched from `clone_url` into `fetch_dir`
+    fn from_fetch(
+        fetch_dir: TempDir,
+        commit_id: Oid,
+        clone_url: &Url,
+        token: Option<&str>,
+    ) -> Self {
+        let repo_path = fetch_dir.path().to_owned();
+        Self {
+            fetched: true,
+            _fetch_dir: Some(fetch_dir),
+            token: token.map(str::to_owned),
+            ..Self::new(repo_path, commit_id, Some(clone_url.to_string()))
+        }
+    }
+
+    /// fetch refs matching `refspecs` into temporary bare repository, with depth 1
+    ///
+    /// shallow fetch requires `git` command of `MIN_GIT_VERSION` or later, as
+    /// libgit2 does not support it. without such `git`, `full_refspecs` are
+    /// fetched with libgit2 including their history.
+    fn fetch_refs(
+        clone_url: &Url,
+        refspecs: &[&str],
+        full_refspecs: &[&str],
+        label: &str,
+        token: Option<&str>,
+    ) -> Result<(TempDir, Repository), PackageAnalyzeError> {
         let tempdir = TempDir::new()?;
-        let builder = RepoBuilder::new()
-            .branch(tag)
-            .clone(clone_url.as_str(), tempdir.path());
+        let repo = Repository::init_bare(tempdir.path())?;
+        match git_version() {
+            Some(version) if version >= MIN_GIT_VERSION => {
+                Self::shallow_fetch(tempdir.path(), clone_url, refspecs, label, token)?
+            }
+            version => {
+                let (major, minor) = MIN_GIT_VERSION;
+                let reason = match version {
+                    Some((found_major, found_minor)) => format!(
+                        "git {}.{} is older than {}.{}",
+                        found_major, found_minor, major, minor
+                    ),
+                    None => "git command is not found".to_string(),
+                };
+                eprintln!("{}, fetching full history with libgit2", reason);
+                Self::libgit2_fetch(&repo, clone_url, full_refspecs, label, token)?
+            }
+        }
+        Ok((tempdir, repo))
+    }
+
+    /// fetch with `git` command, with depth 1
+    ///
+    /// `token` is given as password of https authentication by credential helper
+    /// reading it from environment, so it never appears in command line.
+    fn shallow_fetch(
+        git_dir: &Path,
+        clone_url: &Url,
+        refspecs: &[&str],
+        label: &str,
+        token: Option<&str>,
+    ) -> Result<(), PackageAnalyzeError> {
+        let mut command = Command::new("git");
+        command
+            .arg("--git-dir")
+            .arg(git_dir)
+            .args(["fetch", "--depth", "1", "--no-tags", "--progress"])
+            .arg(clone_url.as_str())
+            .args(refspecs)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null());
+        if let Some(token) = token {
+            // empty helper first, not to ask helpers of user configuration
+            command
+                .env("GIT_CONFIG_COUNT", "2")
+                .env("GIT_CONFIG_KEY_0", "credential.helper")
+                .env("GIT_CONFIG_VALUE_0", "")
+                .env("GIT_CONFIG_KEY_1", "credential.helper")
+                .env("GIT_CONFIG_VALUE_1", TOKEN_CREDENTIAL_HELPER)
+                .env(TOKEN_ENV, token);
+        }
+        eprintln!("fetching {} from {}", label, clone_url);
+        let context = || format!("fetching {} from {}", label, clone_url);
+        let status = command
+            .status()
+            .map_err(|e| PackageAnalyzeError::with_context(&context(), e.into()))?;
+        if !status.success() {
+            return Err(PackageAnalyzeError::with_context(
+                &context(),
+                std::io::Error::other(format!("git fetch failed: {}", status)).into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// fetch with libgit2, including history of `refspecs`
+    ///
+    /// `token` is given as password of https authentication, as GitHub accepts.
+    fn libgit2_fetch(
+        repo: &Repository,
+        clone_url: &Url,
+        refspecs: &[&str],
+        label: &str,
+        token: Option<&str>,
+    ) -> Result<(), PackageAnalyzeError> {
+        let mut last_percent = None;
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.transfer_progress(|progress| {
+            let total = progress.total_objects().max(1);
+            let percent = progress.received_objects() * 100 / total;
+            if last_percent != Some(percent) {
+                last_percent = Some(percent);
+                eprintln!(
+                    "fetching {} : {}% ({}/{} objects, {} bytes)",
+                    label,
+                    percent,
+                    progress.received_objects(),
+                    progress.total_objects(),
+                    progress.received_bytes()
+                );
+            }
+            true
+        });
+        if let Some(token) = token {
+            let mut tried = false;
+            callbacks.credentials(move |_url, _username, allowed| {
+                // libgit2 calls again when credential is rejected
+                if tried || !allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+                    return Err(git2::Error::from_str("token is not accepted"));
+                }
+                tried = true;
+                Cred::userpass_plaintext("x-access-token", token)
+            });
+        }
+        let mut fetch_options = FetchOptions::new();
+        fetch_options
+            .remote_callbacks(callbacks)
+            .download_tags(AutotagOption::None);
+
+        repo.remote_anonymous(clone_url.as_str())?
+            .fetch(refspecs, Some(&mut fetch_options), None)
+            .map_err(|e| {
+                PackageAnalyzeError::with_context(
+                    &format!("fetching {} from {}", label, clone_url),
+                    e.into(),
+                )
+            })?;
+        Ok(())
+    }
+
+    /// fetch only the tag into temporary bare repository and resolve its commit
+    ///
+    /// other branches and tags are not fetched and no working tree is created.
+    /// fetch is shallow, so only the tagged commit and its tree are transferred,
+    /// not history reachable from the tag.
+    pub fn fetch(
+        clone_url: &Url,
+        tag: &str,
+        token: Option<&str>,
+    ) -> Result<Self, PackageAnalyzeError> {
+        let refspec = format!("+refs/tags/{}:refs/tags/{}", tag, tag);
+        let refspecs = [refspec.as_str()];
+        let (tempdir, repo) = Self::fetch_refs(clone_url, &refspecs, &refspecs, tag, token)?;
+        let commit_id = resolve_tag(&repo, tag)?.id();
         Ok(GitPackage {
-            checkout_dir: tempdir,
+            tag_id: tag_object_id(&repo, tag),
+            ..Self::from_fetch(tempdir, commit_id, clone_url, token)
         })
     }
 
-    pub fn analyze_files(
-        mut self,
-    ) -> Result<HashMap<String, SpdxFileAnalyzeSuccess>, PackageAnalyzeError> {
-        PathPackage::new(self.checkout_dir.path()).analyze_files()
+    /// fetch only the commit into temporary bare repository, with depth 1
+    ///
+    /// servers accept commit id in fetch request by protocol v2, as GitHub does.
+    /// without shallow fetch, all branches and tags are fetched to find the commit.
+    fn fetch_commit(
+        clone_url: &Url,
+        commit_id: Oid,
+        token: Option<&str>,
+    ) -> Result<Self, PackageAnalyzeError> {
+        let commit = commit_id.to_string();
+        let (tempdir, repo) =
+            Self::fetch_refs(clone_url, &[commit.as_str()], ALL_REFSPECS, &commit, token)?;
+        repo.find_commit(commit_id).map_err(|e| {
+            PackageAnalyzeError::with_context(
+                &format!("commit {} not found in {}", commit_id, clone_url),
+                e.into(),
+            )
+        })?;
+        Ok(Self::from_fetch(tempdir, commit_id, clone_url, token))
+    }
+
+    /// use existing local repository, without any network access
+    pub fn open(repo_path: &Path, tag: &str) -> Result<Self, PackageAnalyzeError> {
+        let repo = Repository::open(repo_path).map_err(|e| {
+            PackageAnalyzeError::with_context(&format!("opening {:?}", repo_path), e.into())
+        })?;
+        let commit_id = resolve_tag(&repo, tag)?.id();
+        let origin_url = repo
+            .find_remote("origin")
+            .ok()
+            .and_then(|remote| remote.url().map(str::to_owned));
+        Ok(GitPackage {
+            tag_id: tag_object_id(&repo, tag),
+            ..Self::new(repo_path.to_owned(), commit_id, origin_url)
+        })
+    }
+
+    /// also analyze files of submodules
+    ///
+    /// submodules of fetched repository are fetched from their url, submodules of
+    /// local repository are read from their checkout and never fetched.
+    pub fn analyze_submodules(&mut self) {
+        self.analyze_submodules = true;
+    }
+
+    /// verify signatures of tag and commit with given keys
+    ///
+    /// without keys, signatures are only detected and not verified.
+    pub fn verify_signatures(&mut self, keys: SignatureKeys) {
+        self.signature_keys = keys;
+    }
+
+    /// signature state of annotated tag and commit
+    fn signatures(&self, repo: &Repository) -> Result<Vec<SignatureCheck>, PackageAnalyzeError> {
+        let mut checks = Vec::new();
+        if let Some(tag_id) = self.tag_id {
+            checks.push(self.signature_keys.check_tag(repo, tag_id)?);
+        }
+        checks.push(self.signature_keys.check_commit(repo, self.commit_id)?);
+        Ok(checks)
+    }
+
+    /// repository containing submodule commit, `None` if not available
+    fn submodule_package(
+        &self,
+        repo: &Repository,
+        path: &str,
+        url: Option<&Url>,
+        commit_id: Oid,
+    ) -> Result<Option<GitPackage>, PackageAnalyzeError> {
+        if let Ok(sub_repo) = repo
+            .find_submodule(path.trim_start_matches("./"))
+            .and_then(|submodule| submodule.open())
+        {
+            if sub_repo.find_commit(commit_id).is_ok() {
+                return Ok(Some(GitPackage::new(
+                    sub_repo.path().to_owned(),
+                    commit_id,
+                    url.map(Url::to_string),
+                )));
+            }
+        }
+        match url {
+            Some(url) if self.fetched => {
+                // never send token to other hosts
+                let same_host = self
+                    .remote_url
+                    .as_deref()
+                    .and_then(|remote_url| Url::parse(remote_url).ok())
+                    .is_some_and(|remote_url| remote_url.host() == url.host());
+                let token = self.token.as_deref().filter(|_| same_host);
+                Ok(Some(GitPackage::fetch_commit(url, commit_id, token)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// resolve gitlinks of the commit, and analyze them if requested
+    fn submodules(
+        &self,
+        repo: &Repository,
+        commit: &Commit,
+        blobs: &HashMap<String, (Oid, i32)>,
+    ) -> Result<Vec<GitSubmodule>, PackageAnalyzeError> {
+        let urls = match blobs.get(GIT_MODULES) {
+            Some((id, _)) => submodule::parse_gitmodules(&String::from_utf8_lossy(
+                repo.find_blob(*id)?.content(),
+            )),
+            None => HashMap::new(),
+        };
+
+        let mut submodules = Vec::new();
+        for (path, commit_id) in tree_submodules(commit)? {
+            let configured_url = urls.get(path.trim_start_matches("./"));
+            let url = configured_url
+                .and_then(|url| submodule::resolve_url(self.remote_url.as_deref(), url));
+            let download_location = match &url {
+                Some(url) => format!("git+{}@{}", url, commit_id),
+                None => "NOASSERTION".to_string(),
+            };
+            let (files, not_analyzed) = if self.analyze_submodules {
+                match self.submodule_package(repo, &path, url.as_ref(), commit_id) {
+                    Ok(Some(mut package)) => {
+                        package.use_analyzers(&self.analyzers);
+                        (Some(package.analyze_tree()?), None)
+                    }
+                    Ok(None) => {
+                        let reason = match (configured_url, &url) {
+                            (None, _) => "no url in .gitmodules".to_string(),
+                            (Some(configured_url), None) => {
+                                format!("unsupported submodule url {}", configured_url)
+                            }
+                            (Some(_), Some(_)) => "commit is not available".to_string(),
+                        };
+                        (None, Some(reason))
+                    }
+                    Err(e) => (None, Some(e.to_string())),
+                }
+            } else {
+                (None, None)
+            };
+            submodules.push(GitSubmodule {
+                path,
+                commit_id: commit_id.to_string(),
+                download_location,
+                files,
+                not_analyzed,
+            });
+        }
+        Ok(submodules)
+    }
+
+    fn repository(&self) -> Result<Repository, PackageAnalyzeError> {
+        Ok(Repository::open(&self.repo_path)?)
+    }
+
+    /// analyze files in checkout of the tag
+    ///
+    /// tag is checked out into temporary directory, so working tree and index of
+    /// the repository are left untouched. files marked `export-ignore` are excluded,
+    /// and `export-subst` files are analyzed again with substituted content.
+    pub fn analyze_files(self) -> Result<GitFiles, PackageAnalyzeError> {
+        let repo = self.repository()?;
+        let commit = repo.find_commit(self.commit_id)?;
+        let blobs = tree_blobs(&commit)?;
+
+        let checkout_dir = TempDir::new()?;
+        repo.checkout_tree(
+            commit.as_object(),
+            Some(
+                CheckoutBuilder::new()
+                    .force()
+                    .update_index(false)
+                    .target_dir(checkout_dir.path()),
+            ),
+        )?;
+
+        let mut path_package = PathPackage::new(checkout_dir.path());
+        path_package.append_ignore(Path::new(".git"));
+        path_package.use_analyzers(&self.analyzers);
+        let files = path_package.analyze_files()?;
+
+        self.finish(&repo, &commit, files, &blobs, |path| {
+            Ok(fs::read(
+                checkout_dir.path().join(path.trim_start_matches("./")),
+            )?)
+        })
+    }
+
+    /// analyze blobs in tree of the tag directly from object database
+    ///
+    /// content is byte exact as committed, not affected by line ending
+    /// conversion and filters of working tree. nothing is checked out, so
+    /// fetched repository stays bare and no working tree is written.
+    pub fn analyze_tree(self) -> Result<GitFiles, PackageAnalyzeError> {
+        let repo = self.repository()?;
+        let commit = repo.find_commit(self.commit_id)?;
+        let blobs = tree_blobs(&commit)?;
+
+        let mut files = HashMap::new();
+        for (path, (id, _)) in &blobs {
+            let blob = repo.find_blob(*id)?;
+            let mut file_analyzer = self.analyzers.file_analyzer();
+            std::io::copy(&mut blob.content(), &mut file_analyzer)?;
+            let analyze_result = file_analyzer.finish().map_err(|e| {
+                PackageAnalyzeError::with_context(
+                    &format!("analyzing file {}", path),
+                    PackageAnalyzeError::from(e),
+                )
+            })?;
+            files.insert(path.clone(), analyze_result);
+        }
+
+        self.finish(&repo, &commit, files, &blobs, |path| {
+            Ok(repo.find_blob(blobs[path].0)?.content().to_vec())
+        })
+    }
+
+    /// apply `.gitattributes` of the tag to analyzed files
+    fn finish(
+        &self,
+        repo: &Repository,
+        commit: &Commit,
+        mut files: HashMap<String, SpdxFileAnalyzeSuccess>,
+        blobs: &HashMap<String, (Oid, i32)>,
+        read: impl Fn(&str) -> Result<Vec<u8>, PackageAnalyzeError>,
+    ) -> Result<GitFiles, PackageAnalyzeError> {
+        let attributes = load_attributes(repo, blobs)?;
+        files.retain(|path, _| !attributes.is_export_ignore(path.trim_start_matches("./")));
+
+        let mut archive_files = HashMap::new();
+        let mut unverifiable = HashSet::new();
+        for path in files.keys() {
+            let relative = path.trim_start_matches("./");
+            if attributes.get(relative, false, "export-subst") != AttrState::Set {
+                continue;
+            }
+            let content = read(path)?;
+            match subst::expand(&content, commit) {
+                Some(expanded) if expanded != content => {
+                    let mut file_analyzer = self.analyzers.file_analyzer();
+                    std::io::copy(&mut expanded.as_slice(), &mut file_analyzer)?;
+                    archive_files.insert(path.clone(), file_analyzer.finish()?);
+                }
+                Some(_) => {}
+                None => {
+                    unverifiable.insert(path.clone());
+                }
+            }
+        }
+
+        let odb = repo.odb()?;
+        let mut lfs = HashMap::new();
+        for path in files.keys() {
+            let id = match blobs.get(path) {
+                Some((id, _)) => *id,
+                None => continue,
+            };
+            let (size, _) = odb.read_header(id)?;
+            if size > lfs::MAX_POINTER_SIZE {
+                continue;
+            }
+            if let Some(pointer) = lfs::parse_pointer(repo.find_blob(id)?.content()) {
+                lfs.insert(path.clone(), pointer);
+            }
+        }
+
+        let submodules = self.submodules(repo, commit, blobs)?;
+        let signatures = self.signatures(repo)?;
+        let blobs = files
+            .keys()
+            .filter_map(|path| {
+                let (id, mode) = blobs.get(path)?;
+                Some((
+                    path.clone(),
+                    GitBlob {
+                        id: id.to_string(),
+                        mode: *mode,
+                    },
+                ))
+            })
+            .collect();
+
+        Ok(GitFiles {
+            files,
+            archive_files,
+            unverifiable,
+            lfs,
+            blobs,
+            commit_id: self.commit_id.to_string(),
+            download_location: self.download_location.clone(),
+            submodules,
+            signatures,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::Write,
+        path::{Path, PathBuf},
+        process::{Command, Stdio},
+    };
+
+    use git2::{IndexAddOption, ObjectType, Oid, Repository, Signature};
+    use tempfile::TempDir;
+    use url::Url;
+
+    use crate::packages::{
+        git::{
+            parse_git_version, resolve_tag,
+            signature::{SignatureFormat, SignatureOutcome},
+            ALL_REFSPECS, MIN_GIT_VERSION,
+        },
+        GitPackage, SignatureKeys,
+    };
+
+    fn commit_files(repo: &Repository, files: &[(&str, &str)]) -> Oid {
+        let workdir = repo.workdir().unwrap();
+        for (path, content) in files {
+            let path = workdir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("tester", "tester@example.com").unwrap();
+        let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+        let parents = parent.iter().collect::<Vec<_>>();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "fixture",
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
+
+    /// repository with tag `v1.0.0` (annotated) and `light` (lightweight),
+    /// one commit before and one more commit after the tags
+    fn fixture_repo() -> (TempDir, Oid) {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_files(&repo, &[("README.md", "initial\n")]);
+        let tagged = commit_files(
+            &repo,
+            &[
+                ("README.md", "fixture\n"),
+                ("src/lib.rs", "// SPDX-License-Identifier: MIT\n"),
+                ("ci/build.sh", "#!/bin/sh\n"),
+                (".gitattributes", "/ci export-ignore\n"),
+            ],
+        );
+        let object = repo.find_object(tagged, None).unwrap();
+        let signature = Signature::now("tester", "tester@example.com").unwrap();
+        repo.tag("v1.0.0", &object, &signature, "release", false)
+            .unwrap();
+        repo.tag_lightweight("light", &object, false).unwrap();
+        commit_files(&repo, &[("README.md", "changed after tag\n")]);
+        (dir, tagged)
+    }
+
+    /// ed25519 key without passphrase, returns private key path and public key
+    fn ssh_key(dir: &Path, name: &str) -> (PathBuf, String) {
+        let key = dir.join(name);
+        let status = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-C", name, "-f"])
+            .arg(&key)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let public_key = fs::read_to_string(key.with_extension("pub")).unwrap();
+        (key, public_key)
+    }
+
+    /// ssh signature of payload as `git` creates with `gpg.format=ssh`
+    fn ssh_sign(key: &Path, payload: &[u8]) -> String {
+        let mut child = Command::new("ssh-keygen")
+            .args(["-Y", "sign", "-n", "git", "-f"])
+            .arg(key)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(payload).unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    fn check_fixture_files(path: &Path, tag: &str, tagged: Oid, tree: bool) {
+        let package = GitPackage::open(path, tag).unwrap();
+        let git_files = if tree {
+            package.analyze_tree().unwrap()
+        } else {
+            package.analyze_files().unwrap()
+        };
+        assert_eq!(git_files.commit_id, tagged.to_string());
+        assert_eq!(git_files.download_location, "NOASSERTION");
+
+        let mut paths = git_files.files.keys().cloned().collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec!["./.gitattributes", "./README.md", "./src/lib.rs"]
+        );
+        // content of the tag, not of HEAD ("fixture\n")
+        assert_eq!(
+            git_files.files["./README.md"].sha1_checksum().value,
+            "7218324797ea94e3d5a1d421e6d85ee3366370dd"
+        );
+        assert_eq!(git_files.blobs["./src/lib.rs"].mode, 0o100644);
+    }
+
+    #[test]
+    fn test_open_local_repository() {
+        let (dir, tagged) = fixture_repo();
+        check_fixture_files(dir.path(), "v1.0.0", tagged, true);
+        check_fixture_files(dir.path(), "light", tagged, true);
+        check_fixture_files(dir.path(), "v1.0.0", tagged, false);
+        assert!(GitPackage::open(dir.path(), "no-such-tag").is_err());
+
+        let git_files = GitPackage::open(dir.path(), "v1.0.0")
+            .unwrap()
+            .analyze_tree()
+            .unwrap();
+        let signatures = git_files
+            .signatures
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            signatures,
+            vec![
+                "tag v1.0.0 is not signed".to_string(),
+                format!("commit {} is not signed", tagged)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_ssh_signatures() {
+        let key_dir = TempDir::new().unwrap();
+        let (trusted, trusted_public_key) = ssh_key(key_dir.path(), "trusted");
+        let (untrusted, _) = ssh_key(key_dir.path(), "untrusted");
+        let allowed_signers = key_dir.path().join("allowed_signers");
+        fs::write(
+            &allowed_signers,
+            format!("tester@example.com {}", trusted_public_key),
+        )
+        .unwrap();
+
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_files(&repo, &[("README.md", "signed\n")]);
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        // commit signed by key which is not in allowed signers
+        let signature = Signature::now("tester", "tester@example.com").unwrap();
+        let buffer = repo
+            .commit_create_buffer(
+                &signature,
+                &signature,
+                "signed",
+                &head.tree().unwrap(),
+                &[&head],
+            )
+            .unwrap();
+        let commit_signature = ssh_sign(&untrusted, &buffer);
+        let commit_id = repo
+            .commit_signed(buffer.as_str().unwrap(), &commit_signature, None)
+            .unwrap();
+        // tag signed by trusted key, signature is appended to tag message
+        let tag = format!(
+            "object {}\ntype commit\ntag v1\ntagger tester <tester@example.com> 1656633600 +0900\n\nrelease\n",
+            commit_id
+        );
+        let tag_signature = ssh_sign(&trusted, tag.as_bytes());
+        let tag_id = repo
+            .odb()
+            .unwrap()
+            .write(
+                ObjectType::Tag,
+                format!("{}{}", tag, tag_signature).as_bytes(),
+            )
+            .unwrap();
+        repo.reference("refs/tags/v1", tag_id, false, "signed tag")
+            .unwrap();
+
+        let mut package = GitPackage::open(dir.path(), "v1").unwrap();
+        package.verify_signatures(SignatureKeys {
+            gpg_keyring: None,
+            allowed_signers: Some(allowed_signers),
+        });
+        let git_files = package.analyze_tree().unwrap();
+        let outcomes = git_files
+            .signatures
+            .iter()
+            .map(|check| (check.subject.clone(), check.outcome.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            vec![
+                (
+                    "tag v1".to_string(),
+                    SignatureOutcome::Verified {
+                        format: SignatureFormat::Ssh,
+                        signer: "tester@example.com".to_string(),
+                    }
+                ),
+                (
+                    format!("commit {}", commit_id),
+                    SignatureOutcome::Failed {
+                        format: SignatureFormat::Ssh,
+                        reason: "signing key is not in allowed signers".to_string(),
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fetch_single_tag() {
+        let (dir, tagged) = fixture_repo();
+        let url = Url::from_file_path(dir.path()).unwrap();
+        let package = GitPackage::fetch(&url, "v1.0.0", None).unwrap();
+
+        let fetched = Repository::open(&package.repo_path).unwrap();
+        assert!(fetched.is_bare());
+        assert!(fetched.find_reference("refs/tags/light").is_err());
+        // shallow, history before the tagged commit is not fetched
+        assert!(fetched.is_shallow());
+        let parent_id = fetched.find_commit(tagged).unwrap().parent_id(0).unwrap();
+        assert!(fetched.find_commit(parent_id).is_err());
+        assert!(Repository::open(dir.path())
+            .unwrap()
+            .find_commit(parent_id)
+            .is_ok());
+
+        let git_files = package.analyze_files().unwrap();
+        assert_eq!(git_files.commit_id, tagged.to_string());
+        assert_eq!(
+            git_files.download_location,
+            format!("git+{}@{}", url, tagged)
+        );
+        assert!(git_files.files.contains_key("./src/lib.rs"));
+    }
+
+    #[test]
+    fn test_parse_git_version() {
+        assert_eq!(parse_git_version("git version 2.39.2\n"), Some((2, 39)));
+        assert_eq!(
+            parse_git_version("git version 2.37.1 (Apple Git-137.1)"),
+            Some((2, 37))
+        );
+        assert_eq!(
+            parse_git_version("git version 2.31.1.windows.1"),
+            Some((2, 31))
+        );
+        assert_eq!(parse_git_version("command not found"), None);
+        assert!(Some((2, 30)) < Some(MIN_GIT_VERSION));
+    }
+
+    #[test]
+    fn test_libgit2_fetch() {
+        let (dir, tagged) = fixture_repo();
+        let url = Url::from_file_path(dir.path()).unwrap();
+        let fetch_dir = TempDir::new().unwrap();
+        let fetched = Repository::init_bare(fetch_dir.path()).unwrap();
+        GitPackage::libgit2_fetch(&fetched, &url, ALL_REFSPECS, "all", None).unwrap();
+
+        assert_eq!(resolve_tag(&fetched, "v1.0.0").unwrap().id(), tagged);
+        // not shallow, history is fetched too
+        assert!(!fetched.is_shallow());
+        let parent_id = fetched.find_commit(tagged).unwrap().parent_id(0).unwrap();
+        assert!(fetched.find_commit(parent_id).is_ok());
+    }
+
+    #[test]
+    fn test_tree_mode_without_working_tree() {
+        let (dir, tagged) = fixture_repo();
+        let url = Url::from_file_path(dir.path()).unwrap();
+        let mut package = GitPackage::fetch(&url, "v1.0.0", None).unwrap();
+        let repo_path = package.repo_path.clone();
+        // keep fetched repository after the package is consumed
+        let _fetch_dir = package._fetch_dir.take();
+        let fetched = Repository::open(&repo_path).unwrap();
+        assert!(fetched.is_bare());
+        assert!(fetched.workdir().is_none());
+
+        let entries = |path: &Path| {
+            let mut entries = fs::read_dir(path)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name())
+                .collect::<Vec<_>>();
+            entries.sort();
+            entries
+        };
+        let before = entries(&repo_path);
+        let git_files = package.analyze_tree().unwrap();
+        assert_eq!(git_files.commit_id, tagged.to_string());
+        assert!(git_files.files.contains_key("./README.md"));
+        // no file of the tag is written beside the object database
+        assert_eq!(entries(&repo_path), before);
+        assert!(!repo_path.join("README.md").exists());
+    }
+
+    #[test]
+    fn test_fetch_submodule() {
+        let sub_dir = TempDir::new().unwrap();
+        let sub_repo = Repository::init(sub_dir.path()).unwrap();
+        let sub_commit = commit_files(&sub_repo, &[("lib.c", "// SPDX-License-Identifier: MIT\n")]);
+        // gitlink commit is not tip of any branch
+        commit_files(&sub_repo, &[("lib.c", "changed after gitlink\n")]);
+        let sub_url = Url::from_file_path(sub_dir.path()).unwrap();
+        let missing_commit = Oid::from_str("0123456789abcdef0123456789abcdef01234567").unwrap();
+
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let gitmodules = format!(
+            "[submodule \"vendor\"]\n\tpath = vendor\n\turl = {}\n\
+             [submodule \"missing\"]\n\tpath = missing\n\turl = {}\n\
+             [submodule \"unsupported\"]\n\tpath = unsupported\n\turl = not a url\n",
+            sub_url, sub_url
+        );
+        let head = commit_files(&repo, &[(".gitmodules", &gitmodules)]);
+        let head = repo.find_commit(head).unwrap();
+        let mut builder = repo.treebuilder(Some(&head.tree().unwrap())).unwrap();
+        builder.insert("vendor", sub_commit, 0o160000).unwrap();
+        builder.insert("missing", missing_commit, 0o160000).unwrap();
+        builder.insert("unsupported", sub_commit, 0o160000).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let signature = Signature::now("tester", "tester@example.com").unwrap();
+        let tagged = repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                "add submodule",
+                &tree,
+                &[&head],
+            )
+            .unwrap();
+        repo.tag_lightweight("v1", &repo.find_object(tagged, None).unwrap(), false)
+            .unwrap();
+
+        let url = Url::from_file_path(dir.path()).unwrap();
+        let mut package = GitPackage::fetch(&url, "v1", None).unwrap();
+        package.analyze_submodules();
+        let git_files = package.analyze_tree().unwrap();
+        assert!(!git_files.files.contains_key("./vendor"));
+
+        assert_eq!(git_files.submodules.len(), 3);
+        // failed fetch is reported, not skipped
+        let missing = &git_files.submodules[0];
+        assert_eq!(missing.path, "./missing");
+        assert!(missing.files.is_none());
+        assert!(missing.not_analyzed.is_some());
+        let unsupported = &git_files.submodules[1];
+        assert_eq!(unsupported.path, "./unsupported");
+        assert_eq!(unsupported.download_location, "NOASSERTION");
+        assert_eq!(
+            unsupported.not_analyzed.as_deref(),
+            Some("unsupported submodule url not a url")
+        );
+
+        let submodule = &git_files.submodules[2];
+        assert_eq!(submodule.path, "./vendor");
+        assert!(submodule.not_analyzed.is_none());
+        assert_eq!(submodule.commit_id, sub_commit.to_string());
+        assert_eq!(
+            submodule.download_location,
+            format!("git+{}@{}", sub_url, sub_commit)
+        );
+        let sub_files = submodule.files.as_ref().unwrap();
+        // content of the gitlink commit, not of the branch tip
+        let blob_in_gitlink = sub_repo
+            .find_commit(sub_commit)
+            .unwrap()
+            .tree()
+            .unwrap()
+            .get_name("lib.c")
+            .unwrap()
+            .id();
+        assert_eq!(sub_files.blobs["./lib.c"].id, blob_in_gitlink.to_string());
+    }
+
+    #[test]
+    fn test_lfs_pointer() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let pointer = "version https://git-lfs.github.com/spec/v1\n\
+                       oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\n\
+                       size 12345\n";
+        let tagged = commit_files(&repo, &[("data.bin", pointer), ("README.md", "fixture\n")]);
+        repo.tag_lightweight("v1", &repo.find_object(tagged, None).unwrap(), false)
+            .unwrap();
+
+        let git_files = GitPackage::open(dir.path(), "v1")
+            .unwrap()
+            .analyze_tree()
+            .unwrap();
+        assert_eq!(git_files.lfs.len(), 1);
+        assert_eq!(git_files.lfs["./data.bin"].size, 12345);
     }
 }
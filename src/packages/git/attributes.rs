@@ -0,0 +1,208 @@
+//! minimal `.gitattributes` support
+//!
+//! only what `git archive` needs is implemented: pattern matching by
+//! gitignore style glob and set / unset / value states of attributes.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttrState {
+    Set,
+    Unset,
+    Value(String),
+    Unspecified,
+}
+
+struct Rule {
+    base: String,
+    pattern: String,
+    dir_only: bool,
+    attributes: Vec<(String, AttrState)>,
+}
+
+#[derive(Default)]
+pub struct GitAttributes {
+    rules: Vec<Rule>,
+}
+
+/// gitignore style glob match, `*` and `?` do not match `/`
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            // `**/` matches zero or more directories
+            glob_match(rest, text)
+                || text
+                    .iter()
+                    .enumerate()
+                    .any(|(i, c)| *c == b'/' && glob_match(rest, &text[i + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|i| *i == 0 || text[i - 1] != b'/')
+            .any(|i| glob_match(rest, &text[i..])),
+        [b'?', rest @ ..] => match text {
+            [c, text_rest @ ..] if *c != b'/' => glob_match(rest, text_rest),
+            _ => false,
+        },
+        [b'[', class @ ..] => match (class.iter().position(|c| *c == b']'), text) {
+            (Some(end), [c, text_rest @ ..]) if end > 0 && *c != b'/' => {
+                let (negate, members) = match &class[..end] {
+                    [b'!' | b'^', members @ ..] => (true, members),
+                    members => (false, members),
+                };
+                let mut matched = false;
+                let mut i = 0;
+                while i < members.len() {
+                    if i + 2 < members.len() && members[i + 1] == b'-' {
+                        matched |= members[i] <= *c && *c <= members[i + 2];
+                        i += 3;
+                    } else {
+                        matched |= members[i] == *c;
+                        i += 1;
+                    }
+                }
+                matched != negate && glob_match(&class[end + 1..], text_rest)
+            }
+            _ => false,
+        },
+        [b'\\', escaped, rest @ ..] | [escaped, rest @ ..] => match text {
+            [c, text_rest @ ..] if c == escaped => glob_match(rest, text_rest),
+            _ => false,
+        },
+    }
+}
+
+impl Rule {
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let relative = if self.base.is_empty() {
+            path
+        } else {
+            match path
+                .strip_prefix(self.base.as_str())
+                .and_then(|p| p.strip_prefix('/'))
+            {
+                Some(relative) => relative,
+                None => return false,
+            }
+        };
+        if let Some(anchored) = self.pattern.strip_prefix('/') {
+            glob_match(anchored.as_bytes(), relative.as_bytes())
+        } else if self.pattern.contains('/') {
+            glob_match(self.pattern.as_bytes(), relative.as_bytes())
+        } else {
+            let name = relative.rsplit('/').next().unwrap_or(relative);
+            glob_match(self.pattern.as_bytes(), name.as_bytes())
+        }
+    }
+}
+
+fn parse_attribute(token: &str) -> (String, AttrState) {
+    if let Some(name) = token.strip_prefix('-') {
+        (name.to_owned(), AttrState::Unset)
+    } else if let Some(name) = token.strip_prefix('!') {
+        (name.to_owned(), AttrState::Unspecified)
+    } else if let Some((name, value)) = token.split_once('=') {
+        (name.to_owned(), AttrState::Value(value.to_owned()))
+    } else {
+        (token.to_owned(), AttrState::Set)
+    }
+}
+
+impl GitAttributes {
+    /// add rules of `.gitattributes` placed in `dir` (relative to repository root, "" for root)
+    ///
+    /// files must be added from shallower directory to deeper one, later rules take precedence.
+    pub fn add_file(&mut self, dir: &str, content: &str) {
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut tokens = line.split_whitespace();
+            let pattern = match tokens.next() {
+                Some(pattern) => pattern,
+                None => continue,
+            };
+            let (pattern, dir_only) = match pattern.strip_suffix('/') {
+                Some(pattern) => (pattern, true),
+                None => (pattern, false),
+            };
+            self.rules.push(Rule {
+                base: dir.trim_matches('/').to_owned(),
+                pattern: pattern.to_owned(),
+                dir_only,
+                attributes: tokens.map(parse_attribute).collect(),
+            });
+        }
+    }
+
+    /// state of attribute `name` on `path` (relative to repository root)
+    pub fn get(&self, path: &str, is_dir: bool, name: &str) -> AttrState {
+        self.rules
+            .iter()
+            .rev()
+            .filter(|rule| rule.matches(path, is_dir))
+            .find_map(|rule| {
+                rule.attributes
+                    .iter()
+                    .rev()
+                    .find(|(attr, _)| attr == name)
+                    .map(|(_, state)| state.clone())
+            })
+            .unwrap_or(AttrState::Unspecified)
+    }
+
+    /// `git archive` omits file which itself or any parent directory has `export-ignore`
+    pub fn is_export_ignore(&self, path: &str) -> bool {
+        if self.get(path, false, "export-ignore") == AttrState::Set {
+            return true;
+        }
+        path.match_indices('/')
+            .any(|(i, _)| self.get(&path[..i], true, "export-ignore") == AttrState::Set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::packages::git::attributes::{glob_match, AttrState, GitAttributes};
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"*.rs", b"lib.rs"));
+        assert!(!glob_match(b"*.rs", b"src/lib.rs"));
+        assert!(glob_match(b"src/*.rs", b"src/lib.rs"));
+        assert!(glob_match(b"**/test", b"test"));
+        assert!(glob_match(b"**/test", b"a/b/test"));
+        assert!(glob_match(b"a/**/b", b"a/b"));
+        assert!(glob_match(b"a/**/b", b"a/x/y/b"));
+        assert!(glob_match(b"a/**", b"a/x/y"));
+        assert!(glob_match(b"file?.[ch]", b"file1.c"));
+        assert!(!glob_match(b"file?.[!ch]", b"file1.c"));
+        assert!(glob_match(b"[a-c]x", b"bx"));
+    }
+
+    #[test]
+    fn test_export_ignore() {
+        let mut attributes = GitAttributes::default();
+        attributes.add_file(
+            "",
+            "# comment\n/.github export-ignore\n*.bin -diff export-ignore\ntests/ export-ignore\n",
+        );
+        attributes.add_file("docs", "*.md export-ignore\nkeep.bin -export-ignore\n");
+
+        assert!(attributes.is_export_ignore(".github/workflows/rust.yml"));
+        assert!(attributes.is_export_ignore("data/blob.bin"));
+        assert!(attributes.is_export_ignore("tests/fixture.rs"));
+        assert!(attributes.is_export_ignore("docs/readme.md"));
+        assert!(!attributes.is_export_ignore("readme.md"));
+        assert!(!attributes.is_export_ignore("docs/keep.bin"));
+        assert!(!attributes.is_export_ignore("src/lib.rs"));
+        assert!(!attributes.is_export_ignore("src/tests"));
+        assert_eq!(
+            attributes.get("data/blob.bin", false, "diff"),
+            AttrState::Unset
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! Git LFS pointer files
+
+/// pointer file is small, bigger blob is never a pointer
+pub const MAX_POINTER_SIZE: usize = 1024;
+
+const VERSION_LINE: &str = "version https://git-lfs.github.com/spec/v1";
+
+/// LFS object which pointer file refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LfsPointer {
+    /// sha256 of object content, lower hex
+    pub oid: String,
+    pub size: u64,
+}
+
+/// parse pointer file, `None` if content is not a pointer
+pub fn parse_pointer(content: &[u8]) -> Option<LfsPointer> {
+    if content.len() > MAX_POINTER_SIZE {
+        return None;
+    }
+    let content = std::str::from_utf8(content).ok()?;
+    let mut lines = content.lines();
+    if lines.next()? != VERSION_LINE {
+        return None;
+    }
+    let mut oid = None;
+    let mut size = None;
+    for line in lines {
+        match line.split_once(' ')? {
+            ("oid", value) => {
+                let hash = value.strip_prefix("sha256:")?;
+                if hash.len() != 64 || !hash.bytes().all(|c| c.is_ascii_hexdigit()) {
+                    return None;
+                }
+                oid = Some(hash.to_ascii_lowercase());
+            }
+            ("size", value) => size = Some(value.parse().ok()?),
+            _ => {}
+        }
+    }
+    Some(LfsPointer {
+        oid: oid?,
+        size: size?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::packages::git::lfs::parse_pointer;
+
+    #[test]
+    fn test_parse_pointer() {
+        let pointer = parse_pointer(
+            b"version https://git-lfs.github.com/spec/v1\n\
+              oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\n\
+              size 12345\n",
+        )
+        .unwrap();
+        assert_eq!(
+            pointer.oid,
+            "4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393"
+        );
+        assert_eq!(pointer.size, 12345);
+
+        assert!(parse_pointer(b"fixture\n").is_none());
+        assert!(parse_pointer(
+            b"version https://git-lfs.github.com/spec/v1\noid sha256:1234\nsize 1\n"
+        )
+        .is_none());
+    }
+}
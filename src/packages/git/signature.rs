@@ -0,0 +1,333 @@
+//! verification of signed tags and commits
+//!
+//! signatures are verified by `gpg` and `ssh-keygen` as git does, only
+//! against keys given explicitly, never against user's default keyring.
+
+use std::{
+    fmt::{self, Display},
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use git2::{Oid, Repository};
+use tempfile::NamedTempFile;
+
+const GPG_SIGNATURE: &[u8] = b"-----BEGIN PGP SIGNATURE-----";
+const SSH_SIGNATURE: &[u8] = b"-----BEGIN SSH SIGNATURE-----";
+
+/// trusted keys to verify signatures with
+#[derive(Debug, Clone, Default)]
+pub struct SignatureKeys {
+    /// gpg keyring file
+    pub gpg_keyring: Option<PathBuf>,
+    /// ssh allowed signers file, as `gpg.ssh.allowedSignersFile` of git
+    pub allowed_signers: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureFormat {
+    Gpg,
+    Ssh,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureOutcome {
+    Unsigned,
+    Verified {
+        format: SignatureFormat,
+        signer: String,
+    },
+    /// signature is bad or signer is not trusted
+    Failed {
+        format: SignatureFormat,
+        reason: String,
+    },
+    /// signature could not be checked
+    NotVerified {
+        format: SignatureFormat,
+        reason: String,
+    },
+}
+
+/// signature state of tag or commit
+#[derive(Debug, Clone)]
+pub struct SignatureCheck {
+    /// e.g. `tag v1.0.0`, `commit <sha>`
+    pub subject: String,
+    pub outcome: SignatureOutcome,
+}
+
+impl Display for SignatureFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureFormat::Gpg => write!(f, "gpg"),
+            SignatureFormat::Ssh => write!(f, "ssh"),
+        }
+    }
+}
+
+impl Display for SignatureCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.outcome {
+            SignatureOutcome::Unsigned => write!(f, "{} is not signed", self.subject),
+            SignatureOutcome::Verified { format, signer } => write!(
+                f,
+                "{} has good {} signature by {}",
+                self.subject, format, signer
+            ),
+            SignatureOutcome::Failed { format, reason } => write!(
+                f,
+                "{} {} signature verification failed: {}",
+                self.subject, format, reason
+            ),
+            SignatureOutcome::NotVerified { format, reason } => write!(
+                f,
+                "{} has {} signature, not verified: {}",
+                self.subject, format, reason
+            ),
+        }
+    }
+}
+
+fn signature_format(signature: &[u8]) -> Option<SignatureFormat> {
+    if signature.starts_with(GPG_SIGNATURE) {
+        Some(SignatureFormat::Gpg)
+    } else if signature.starts_with(SSH_SIGNATURE) {
+        Some(SignatureFormat::Ssh)
+    } else {
+        None
+    }
+}
+
+/// split raw tag object into signed payload and signature appended to message
+fn split_tag_signature(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    [GPG_SIGNATURE, SSH_SIGNATURE]
+        .iter()
+        .filter_map(|marker| {
+            data.windows(marker.len())
+                .position(|w| w == *marker)
+                .filter(|start| *start == 0 || data[start - 1] == b'\n')
+        })
+        .min()
+        .map(|start| data.split_at(start))
+}
+
+/// run command with payload as stdin
+fn run_with_stdin(command: &mut Command, payload: &[u8]) -> std::io::Result<(bool, String)> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(payload)?;
+    let output = child.wait_with_output()?;
+    Ok((
+        output.status.success(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    ))
+}
+
+fn verify_gpg(keyring: &Path, payload: &[u8], signature: &Path) -> SignatureOutcome {
+    let format = SignatureFormat::Gpg;
+    // relative keyring is looked up in gpg home directory
+    let keyring = keyring
+        .canonicalize()
+        .unwrap_or_else(|_| keyring.to_owned());
+    let result = run_with_stdin(
+        Command::new("gpg")
+            .args(["--batch", "--no-default-keyring", "--keyring"])
+            .arg(&keyring)
+            .args(["--status-fd", "1", "--verify"])
+            .arg(signature)
+            .arg("-"),
+        payload,
+    );
+    let (_, status) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            return SignatureOutcome::NotVerified {
+                format,
+                reason: format!("running gpg: {}", e),
+            }
+        }
+    };
+    let mut reason = "no valid signature".to_string();
+    for line in status.lines() {
+        let line = match line.strip_prefix("[GNUPG:] ") {
+            Some(line) => line,
+            None => continue,
+        };
+        match line.split_once(' ') {
+            Some(("GOODSIG", signer)) => {
+                return SignatureOutcome::Verified {
+                    format,
+                    signer: signer.to_string(),
+                }
+            }
+            Some(("BADSIG", _)) => reason = "bad signature".to_string(),
+            Some(("NO_PUBKEY", key)) => reason = format!("public key {} not in keyring", key),
+            Some((status @ ("EXPKEYSIG" | "REVKEYSIG" | "EXPSIG"), signer)) => {
+                reason = format!("{} {}", status, signer)
+            }
+            _ => {}
+        }
+    }
+    SignatureOutcome::Failed { format, reason }
+}
+
+fn verify_ssh(allowed_signers: &Path, payload: &[u8], signature: &Path) -> SignatureOutcome {
+    let format = SignatureFormat::Ssh;
+    let principals = Command::new("ssh-keygen")
+        .args(["-Y", "find-principals", "-f"])
+        .arg(allowed_signers)
+        .arg("-s")
+        .arg(signature)
+        .stderr(Stdio::null())
+        .output();
+    let principal = match principals {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()
+            .map(str::to_string),
+        Ok(_) => None,
+        Err(e) => {
+            return SignatureOutcome::NotVerified {
+                format,
+                reason: format!("running ssh-keygen: {}", e),
+            }
+        }
+    };
+    let principal = match principal {
+        Some(principal) => principal,
+        None => {
+            return SignatureOutcome::Failed {
+                format,
+                reason: "signing key is not in allowed signers".to_string(),
+            }
+        }
+    };
+    let result = run_with_stdin(
+        Command::new("ssh-keygen")
+            .args(["-Y", "verify", "-n", "git", "-f"])
+            .arg(allowed_signers)
+            .arg("-I")
+            .arg(&principal)
+            .arg("-s")
+            .arg(signature),
+        payload,
+    );
+    match result {
+        Ok((true, _)) => SignatureOutcome::Verified {
+            format,
+            signer: principal,
+        },
+        Ok((false, _)) => SignatureOutcome::Failed {
+            format,
+            reason: "bad signature".to_string(),
+        },
+        Err(e) => SignatureOutcome::NotVerified {
+            format,
+            reason: format!("running ssh-keygen: {}", e),
+        },
+    }
+}
+
+impl SignatureKeys {
+    fn verify(&self, payload: &[u8], signature: &[u8]) -> SignatureOutcome {
+        let format = match signature_format(signature) {
+            Some(format) => format,
+            None => return SignatureOutcome::Unsigned,
+        };
+        let key_file = match format {
+            SignatureFormat::Gpg => &self.gpg_keyring,
+            SignatureFormat::Ssh => &self.allowed_signers,
+        };
+        let key_file = match key_file {
+            Some(key_file) => key_file,
+            None => {
+                return SignatureOutcome::NotVerified {
+                    format,
+                    reason: "no trusted keys given".to_string(),
+                }
+            }
+        };
+        let signature_file = NamedTempFile::new().and_then(|mut file| {
+            file.write_all(signature)?;
+            Ok(file)
+        });
+        let signature_file = match signature_file {
+            Ok(file) => file,
+            Err(e) => {
+                return SignatureOutcome::NotVerified {
+                    format,
+                    reason: format!("writing signature: {}", e),
+                }
+            }
+        };
+        match format {
+            SignatureFormat::Gpg => verify_gpg(key_file, payload, signature_file.path()),
+            SignatureFormat::Ssh => verify_ssh(key_file, payload, signature_file.path()),
+        }
+    }
+
+    /// check signature of annotated tag object
+    pub fn check_tag(&self, repo: &Repository, tag_id: Oid) -> Result<SignatureCheck, git2::Error> {
+        let tag = repo.find_tag(tag_id)?;
+        let subject = format!("tag {}", tag.name().unwrap_or_default());
+        let odb = repo.odb()?;
+        let object = odb.read(tag_id)?;
+        let outcome = match split_tag_signature(object.data()) {
+            Some((payload, signature)) => self.verify(payload, signature),
+            None => SignatureOutcome::Unsigned,
+        };
+        Ok(SignatureCheck { subject, outcome })
+    }
+
+    /// check signature of commit
+    pub fn check_commit(
+        &self,
+        repo: &Repository,
+        commit_id: Oid,
+    ) -> Result<SignatureCheck, git2::Error> {
+        let subject = format!("commit {}", commit_id);
+        let outcome = match repo.extract_signature(&commit_id, None) {
+            Ok((signature, payload)) => self.verify(&payload, &signature),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => SignatureOutcome::Unsigned,
+            Err(e) => return Err(e),
+        };
+        Ok(SignatureCheck { subject, outcome })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::packages::git::signature::{split_tag_signature, SignatureKeys, SignatureOutcome};
+
+    #[test]
+    fn test_split_tag_signature() {
+        let tag = b"object 1234\ntype commit\ntag v1\n\nrelease\n\
+                    -----BEGIN SSH SIGNATURE-----\nU1NIU0lH\n-----END SSH SIGNATURE-----\n";
+        let (payload, signature) = split_tag_signature(tag).unwrap();
+        assert_eq!(payload, b"object 1234\ntype commit\ntag v1\n\nrelease\n");
+        assert!(signature.starts_with(b"-----BEGIN SSH SIGNATURE-----"));
+        assert!(split_tag_signature(b"object 1234\n\nrelease\n").is_none());
+    }
+
+    #[test]
+    fn test_signed_without_keys_is_not_verified() {
+        let keys = SignatureKeys::default();
+        assert_eq!(
+            keys.verify(b"payload", b"no signature"),
+            SignatureOutcome::Unsigned
+        );
+        assert!(matches!(
+            keys.verify(b"payload", b"-----BEGIN PGP SIGNATURE-----\n"),
+            SignatureOutcome::NotVerified { .. }
+        ));
+    }
+}
@@ -0,0 +1,118 @@
+//! `.gitmodules` parsing
+
+use std::collections::HashMap;
+
+use url::Url;
+
+/// map submodule path to its url
+pub fn parse_gitmodules(content: &str) -> HashMap<String, String> {
+    let mut sections: Vec<(Option<String>, Option<String>)> = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') {
+            sections.push((None, None));
+            continue;
+        }
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim().trim_matches('"')),
+            None => continue,
+        };
+        if let Some((path, url)) = sections.last_mut() {
+            match key {
+                "path" => *path = Some(value.to_owned()),
+                "url" => *url = Some(value.to_owned()),
+                _ => {}
+            }
+        }
+    }
+    sections
+        .into_iter()
+        .filter_map(|(path, url)| Some((path?.trim_end_matches('/').to_owned(), url?)))
+        .collect()
+}
+
+/// scp-like syntax of git (`git@github.com:owner/lib.git`) as `ssh://` url
+///
+/// as git does, url without `://` which has colon before first slash is scp-like,
+/// except single letter host of Windows drive.
+fn scp_like_url(url: &str) -> Option<Url> {
+    if url.contains("://") {
+        return None;
+    }
+    let (host, path) = url.split_once(':')?;
+    if host.len() < 2 || host.contains('/') || path.is_empty() {
+        return None;
+    }
+    Url::parse(&format!("ssh://{}/{}", host, path.trim_start_matches('/'))).ok()
+}
+
+/// resolve submodule url, relative url (`../other.git`) is relative to url of superproject
+pub fn resolve_url(superproject_url: Option<&str>, url: &str) -> Option<Url> {
+    if url.starts_with("./") || url.starts_with("../") {
+        let base = Url::parse(&format!("{}/", superproject_url?.trim_end_matches('/'))).ok()?;
+        base.join(url).ok()
+    } else {
+        scp_like_url(url).or_else(|| Url::parse(url).ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::packages::git::submodule::{parse_gitmodules, resolve_url};
+
+    #[test]
+    fn test_parse_gitmodules() {
+        let modules = parse_gitmodules(
+            "[submodule \"vendor/lib\"]\n\
+             \tpath = vendor/lib\n\
+             \turl = https://github.com/owner/lib.git\n\
+             [submodule \"docs\"]\n\
+             \turl = ../docs.git\n\
+             \tpath = docs\n",
+        );
+        assert_eq!(modules.len(), 2);
+        assert_eq!(modules["vendor/lib"], "https://github.com/owner/lib.git");
+        assert_eq!(modules["docs"], "../docs.git");
+    }
+
+    #[test]
+    fn test_resolve_relative_url() {
+        let base = Some("https://github.com/owner/repo.git");
+        assert_eq!(
+            resolve_url(base, "../docs.git").unwrap().as_str(),
+            "https://github.com/owner/docs.git"
+        );
+        assert_eq!(
+            resolve_url(base, "https://example.com/lib.git")
+                .unwrap()
+                .as_str(),
+            "https://example.com/lib.git"
+        );
+        assert!(resolve_url(None, "../docs.git").is_none());
+    }
+
+    #[test]
+    fn test_resolve_scp_like_url() {
+        assert_eq!(
+            resolve_url(None, "git@github.com:owner/lib.git")
+                .unwrap()
+                .as_str(),
+            "ssh://git@github.com/owner/lib.git"
+        );
+        assert_eq!(
+            resolve_url(None, "example.com:/srv/lib.git")
+                .unwrap()
+                .as_str(),
+            "ssh://example.com/srv/lib.git"
+        );
+        assert_eq!(
+            resolve_url(None, "ssh://git@github.com/owner/lib.git")
+                .unwrap()
+                .as_str(),
+            "ssh://git@github.com/owner/lib.git"
+        );
+    }
+}
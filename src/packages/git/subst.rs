@@ -0,0 +1,168 @@
+//! `export-subst` expansion as `git archive` does
+//!
+//! placeholders which depend on state of repository at archive time
+//! can not be reproduced, expansion of such file returns `None`. these are
+//! `%d` ref names, and abbreviated ids `%h`, `%t` and `%p`, as length of them
+//! grows with object count of the repository running `git archive`.
+
+use chrono::{FixedOffset, TimeZone};
+use git2::{Commit, Signature};
+
+const FORMAT_START: &[u8] = b"$Format:";
+
+fn format_date(signature: &Signature, kind: char) -> Option<String> {
+    let when = signature.when();
+    let offset = FixedOffset::east_opt(when.offset_minutes() * 60)?;
+    let date = offset.timestamp_opt(when.seconds(), 0).single()?;
+    let formatted = match kind {
+        'd' => date.format("%a %b %-d %H:%M:%S %Y %z").to_string(),
+        'i' => date.format("%Y-%m-%d %H:%M:%S %z").to_string(),
+        'I' => date.format("%Y-%m-%dT%H:%M:%S%:z").to_string(),
+        't' => when.seconds().to_string(),
+        _ => return None,
+    };
+    Some(formatted)
+}
+
+fn format_person(signature: &Signature, kind: char) -> Option<String> {
+    match kind {
+        'n' => signature.name().map(str::to_owned),
+        'e' => signature.email().map(str::to_owned),
+        _ => format_date(signature, kind),
+    }
+}
+
+/// expand one `$Format:<format>$` body
+fn format_commit(format: &str, commit: &Commit) -> Option<String> {
+    let mut output = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '%' => output.push('%'),
+            'n' => output.push('\n'),
+            'H' => output.push_str(&commit.id().to_string()),
+            'T' => output.push_str(&commit.tree_id().to_string()),
+            'P' => output.push_str(
+                &commit
+                    .parent_ids()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            's' => output.push_str(commit.summary()?),
+            'B' => output.push_str(commit.message()?),
+            'a' => output.push_str(&format_person(&commit.author(), chars.next()?)?),
+            'c' => output.push_str(&format_person(&commit.committer(), chars.next()?)?),
+            _ => return None,
+        }
+    }
+    Some(output)
+}
+
+/// expand all `$Format:...$` in content, `None` if not reproducible
+pub fn expand(content: &[u8], commit: &Commit) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest
+        .windows(FORMAT_START.len())
+        .position(|w| w == FORMAT_START)
+    {
+        let body = &rest[start + FORMAT_START.len()..];
+        let end = match body.iter().position(|c| *c == b'$') {
+            Some(end) => end,
+            None => break,
+        };
+        let format = std::str::from_utf8(&body[..end]).ok()?;
+        output.extend_from_slice(&rest[..start]);
+        output.extend_from_slice(format_commit(format, commit)?.as_bytes());
+        rest = &body[end + 1..];
+    }
+    output.extend_from_slice(rest);
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use git2::{Oid, Repository, Signature, Time};
+    use tempfile::TempDir;
+
+    use crate::packages::git::subst::{expand, format_commit};
+
+    /// bare repository with commit `release v1` and its parent, committed at
+    /// 2022-07-01T00:00:00Z in +09:00
+    fn fixture_repo() -> (TempDir, Repository, Oid) {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        let blob = repo.blob(b"$Format:%H$\n").unwrap();
+        let mut builder = repo.treebuilder(None).unwrap();
+        builder.insert("version.txt", blob, 0o100644).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let signature =
+            Signature::new("tester", "tester@example.com", &Time::new(1656633600, 540)).unwrap();
+        let parent = repo
+            .commit(None, &signature, &signature, "initial", &tree, &[])
+            .unwrap();
+        let parent = repo.find_commit(parent).unwrap();
+        let commit = repo
+            .commit(
+                None,
+                &signature,
+                &signature,
+                "release v1\n\nbody line\n",
+                &tree,
+                &[&parent],
+            )
+            .unwrap();
+        drop((builder, tree, parent));
+        (dir, repo, commit)
+    }
+
+    #[test]
+    fn test_format_commit() {
+        let (_dir, repo, id) = fixture_repo();
+        let commit = repo.find_commit(id).unwrap();
+        let parent_id = commit.parent_id(0).unwrap().to_string();
+        let format = |format: &str| format_commit(format, &commit);
+
+        assert_eq!(format("%H").unwrap(), id.to_string());
+        assert_eq!(format("%T").unwrap(), commit.tree_id().to_string());
+        assert_eq!(format("%P").unwrap(), parent_id);
+        assert_eq!(format("%s").unwrap(), "release v1");
+        assert_eq!(format("%B").unwrap(), "release v1\n\nbody line\n");
+        assert_eq!(
+            format("%an <%ae>%n%cn <%ce>").unwrap(),
+            "tester <tester@example.com>\ntester <tester@example.com>"
+        );
+        assert_eq!(format("%ad").unwrap(), "Fri Jul 1 09:00:00 2022 +0900");
+        assert_eq!(format("%ai").unwrap(), "2022-07-01 09:00:00 +0900");
+        assert_eq!(format("%cI").unwrap(), "2022-07-01T09:00:00+09:00");
+        assert_eq!(format("%ct").unwrap(), "1656633600");
+        assert_eq!(format("100%%").unwrap(), "100%");
+        // ref names and abbreviations depend on repository at archive time
+        assert_eq!(format("%d"), None);
+        assert_eq!(format("%h"), None);
+        assert_eq!(format("%t"), None);
+        assert_eq!(format("%p"), None);
+    }
+
+    #[test]
+    fn test_expand() {
+        let (_dir, repo, id) = fixture_repo();
+        let commit = repo.find_commit(id).unwrap();
+        let expanded = expand(
+            b"version $Format:%H$ ($Format:%s$)\nunclosed $Format:%H\n",
+            &commit,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(expanded).unwrap(),
+            format!("version {} (release v1)\nunclosed $Format:%H\n", id)
+        );
+        assert_eq!(expand(b"$Format:%d$", &commit), None);
+        assert_eq!(expand(b"$Format:%h$", &commit), None);
+    }
+}
@@ -1,23 +1,40 @@
 use std::collections::HashMap;
 
-use ::tar::Archive as Tar;
+use ::tar::{Archive as Tar, EntryType};
 use anyhow::Result;
 use flate2::read::GzDecoder;
 
 use crate::{
-    analyzers::{FileAnalyzer, SpdxFileAnalyzeSuccess, SpdxFileAnalyzer},
-    packages::PackageAnalyzeError,
+    analyzers::{AnalyzerRegistry, FileAnalyzer, SpdxFileAnalyzeSuccess},
+    packages::{normalize_paths, PackageAnalyzeError, UseAnalyzers},
 };
 
 pub struct TarPackage<R: std::io::Read> {
     tar: Tar<GzDecoder<R>>,
+    strip_root: bool,
+    analyzers: AnalyzerRegistry,
+}
+
+impl<R: std::io::Read> UseAnalyzers for TarPackage<R> {
+    fn analyzers_mut(&mut self) -> &mut AnalyzerRegistry {
+        &mut self.analyzers
+    }
 }
 
 impl<R: std::io::Read> TarPackage<R> {
     pub fn from_read(file: R) -> Self {
         let gzdec = GzDecoder::new(file);
         let tar = Tar::new(gzdec);
-        Self { tar }
+        Self {
+            tar,
+            strip_root: false,
+            analyzers: AnalyzerRegistry::default(),
+        }
+    }
+
+    /// strip top level directory shared by all entries
+    pub fn strip_root_directory(&mut self) {
+        self.strip_root = true;
     }
 
     pub fn analyze_files(
@@ -26,11 +43,65 @@ impl<R: std::io::Read> TarPackage<R> {
         let mut files = HashMap::new();
         for entry in self.tar.entries()? {
             let mut file = entry?;
-            let mut file_analyzer = SpdxFileAnalyzer::new();
-            std::io::copy(&mut file, &mut file_analyzer)?;
+            let mut file_analyzer = self.analyzers.file_analyzer();
+            match file.header().entry_type() {
+                // symlink is blob of link target in git, as in zip archive
+                EntryType::Symlink => {
+                    let target = file.link_name_bytes().unwrap_or_default();
+                    std::io::copy(&mut target.as_ref(), &mut file_analyzer)?;
+                }
+                entry_type if entry_type.is_file() => {
+                    std::io::copy(&mut file, &mut file_analyzer)?;
+                }
+                _ => continue,
+            }
             let analyze_result = file_analyzer.finish()?;
-            files.insert(file.path()?.to_str().unwrap().to_owned(), analyze_result);
+            files.insert(file.path()?.to_string_lossy().into_owned(), analyze_result);
         }
-        Ok(files)
+        Ok(normalize_paths(files, self.strip_root))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::{write::GzEncoder, Compression};
+    use tar::{Builder, EntryType, Header};
+
+    use crate::{
+        analyzers::{AnalyzerRegistry, FileAnalyzer},
+        packages::TarPackage,
+    };
+
+    #[test]
+    fn test_symlink_is_link_target() {
+        let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        let mut header = Header::new_gnu();
+        header.set_size(6);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "repo-v1/README", &b"readme"[..])
+            .unwrap();
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Symlink);
+        header.set_size(0);
+        header.set_mode(0o777);
+        builder
+            .append_link(&mut header, "repo-v1/README.md", "README")
+            .unwrap();
+        let archive = builder.into_inner().unwrap().finish().unwrap();
+
+        let mut package = TarPackage::from_read(archive.as_slice());
+        package.strip_root_directory();
+        let files = package.analyze_files().unwrap();
+
+        let mut analyzer = AnalyzerRegistry::default().file_analyzer();
+        std::io::copy(&mut &b"README"[..], &mut analyzer).unwrap();
+        let expected = analyzer.finish().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(
+            files["./README.md"].sha1_checksum().value,
+            expected.sha1_checksum().value
+        );
     }
 }
@@ -3,35 +3,53 @@ use std::collections::HashMap;
 use ::zip::ZipArchive;
 
 use crate::{
-    analyzers::{FileAnalyzer, SpdxFileAnalyzeSuccess, SpdxFileAnalyzer},
-    packages::PackageAnalyzeError,
+    analyzers::{AnalyzerRegistry, FileAnalyzer, SpdxFileAnalyzeSuccess},
+    packages::{normalize_paths, PackageAnalyzeError, UseAnalyzers},
 };
 
 pub struct ZipPackage<R: std::io::Read + std::io::Seek> {
     zip: ZipArchive<R>,
+    strip_root: bool,
+    analyzers: AnalyzerRegistry,
+}
+
+impl<R: std::io::Read + std::io::Seek> UseAnalyzers for ZipPackage<R> {
+    fn analyzers_mut(&mut self) -> &mut AnalyzerRegistry {
+        &mut self.analyzers
+    }
 }
 
 impl<R: std::io::Read + std::io::Seek> ZipPackage<R> {
     pub fn from_read(file: R) -> Result<Self, PackageAnalyzeError> {
         Ok(Self {
             zip: ZipArchive::new(file)?,
+            strip_root: false,
+            analyzers: AnalyzerRegistry::default(),
         })
     }
 
+    /// strip top level directory shared by all entries
+    pub fn strip_root_directory(&mut self) {
+        self.strip_root = true;
+    }
+
     pub fn analyze_files(
         mut self,
     ) -> Result<HashMap<String, SpdxFileAnalyzeSuccess>, PackageAnalyzeError> {
         let mut files = HashMap::new();
         for index in 0..self.zip.len() {
             let mut file = self.zip.by_index(index)?;
-            let mut file_analyzer = SpdxFileAnalyzer::new();
+            if file.is_dir() {
+                continue;
+            }
+            let mut file_analyzer = self.analyzers.file_analyzer();
             std::io::copy(&mut file, &mut file_analyzer)?;
             let analyze_result = file_analyzer.finish()?;
             files.insert(
-                file.mangled_name().to_str().unwrap().to_owned(),
+                file.mangled_name().to_string_lossy().into_owned(),
                 analyze_result,
             );
         }
-        Ok(files)
+        Ok(normalize_paths(files, self.strip_root))
     }
 }
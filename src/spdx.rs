@@ -1,4 +1,12 @@
-use spdx_rs::models::{FileInformation, PackageInformation, Relationship, RelationshipType, SPDX};
+use std::io::Write;
+
+use chrono::Utc;
+use spdx_rs::models::{
+    Annotation, AnnotationType, FileInformation, PackageInformation, Relationship,
+    RelationshipType, SPDX,
+};
+
+mod tag_value;
 
 pub struct SpdxDocument {
     spdx_id: i32,
@@ -7,9 +15,15 @@ pub struct SpdxDocument {
 
 impl SpdxDocument {
     pub fn new(name: &str) -> Self {
+        let mut document = SPDX::new(name);
+        document
+            .document_creation_information
+            .creation_info
+            .creators
+            .push(format!("Tool: sbom-ghr-{}", env!("CARGO_PKG_VERSION")));
         Self {
             spdx_id: 0,
-            document: SPDX::new(name),
+            document,
         }
     }
 
@@ -30,7 +44,61 @@ impl SpdxDocument {
     }
 
     pub fn push_contains(&mut self, package_id: &str, file_id: &str) {
-        let relation = Relationship::new(package_id, &file_id, RelationshipType::Contains, None);
+        self.push_relationship(package_id, file_id, RelationshipType::Contains);
+    }
+
+    pub fn push_relationship(
+        &mut self,
+        element_id: &str,
+        related_element_id: &str,
+        relationship_type: RelationshipType,
+    ) {
+        let relation = Relationship::new(element_id, related_element_id, relationship_type, None);
         self.document.relationships.push(relation);
     }
+
+    /// relationship with comment, to describe `OTHER` relationship
+    pub fn push_relationship_with_comment(
+        &mut self,
+        element_id: &str,
+        related_element_id: &str,
+        relationship_type: RelationshipType,
+        comment: &str,
+    ) {
+        let relation = Relationship::new(
+            element_id,
+            related_element_id,
+            relationship_type,
+            Some(comment.to_string()),
+        );
+        self.document.relationships.push(relation);
+    }
+
+    /// annotate element by this tool
+    pub fn push_annotation(&mut self, spdx_id: &str, comment: &str) {
+        self.document.annotations.push(Annotation {
+            annotator: format!("Tool: sbom-ghr-{}", env!("CARGO_PKG_VERSION")),
+            annotation_date: Utc::now(),
+            annotation_type: AnnotationType::Other,
+            spdx_identifier_reference: Some(spdx_id.to_string()),
+            annotation_comment: comment.to_string(),
+        });
+    }
+
+    /// write document as SPDX JSON
+    pub fn write_json<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        serde_json::to_writer_pretty(&mut writer, &self.document)?;
+        writeln!(writer)?;
+        writer.flush()
+    }
+
+    /// write document as SPDX tag-value
+    pub fn write_tag_value<W: Write>(&self, writer: W) -> std::io::Result<()> {
+        tag_value::write_tag_value(&self.to_json_value()?, writer)
+    }
+
+    /// SPDX JSON representation of document
+    pub fn to_json_value(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::to_value(&self.document)
+    }
 }
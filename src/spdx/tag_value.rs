@@ -0,0 +1,313 @@
+//! SPDX tag-value (`.spdx`) writer
+//!
+//! rendered from SPDX JSON representation of document, so field names follows
+//! SPDX specification rather than in memory model.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+use serde_json::Value;
+
+const DOCUMENT_FIELDS: &[(&str, &str)] = &[
+    ("spdxVersion", "SPDXVersion"),
+    ("dataLicense", "DataLicense"),
+    ("SPDXID", "SPDXID"),
+    ("name", "DocumentName"),
+    ("documentNamespace", "DocumentNamespace"),
+    ("externalDocumentRefs", "ExternalDocumentRef"),
+    ("comment", "DocumentComment"),
+];
+
+const CREATION_INFO_FIELDS: &[(&str, &str)] = &[
+    ("licenseListVersion", "LicenseListVersion"),
+    ("creators", "Creator"),
+    ("created", "Created"),
+    ("comment", "CreatorComment"),
+];
+
+const PACKAGE_FIELDS: &[(&str, &str)] = &[
+    ("name", "PackageName"),
+    ("SPDXID", "SPDXID"),
+    ("versionInfo", "PackageVersion"),
+    ("packageFileName", "PackageFileName"),
+    ("supplier", "PackageSupplier"),
+    ("originator", "PackageOriginator"),
+    ("downloadLocation", "PackageDownloadLocation"),
+    ("filesAnalyzed", "FilesAnalyzed"),
+    ("packageVerificationCode", "PackageVerificationCode"),
+    ("checksums", "PackageChecksum"),
+    ("homepage", "PackageHomePage"),
+    ("sourceInfo", "PackageSourceInfo"),
+    ("licenseConcluded", "PackageLicenseConcluded"),
+    ("licenseInfoFromFiles", "PackageLicenseInfoFromFiles"),
+    ("licenseDeclared", "PackageLicenseDeclared"),
+    ("licenseComments", "PackageLicenseComments"),
+    ("copyrightText", "PackageCopyrightText"),
+    ("summary", "PackageSummary"),
+    ("description", "PackageDescription"),
+    ("comment", "PackageComment"),
+    ("externalRefs", "ExternalRef"),
+    ("attributionTexts", "PackageAttributionText"),
+];
+
+const FILE_FIELDS: &[(&str, &str)] = &[
+    ("fileName", "FileName"),
+    ("SPDXID", "SPDXID"),
+    ("fileTypes", "FileType"),
+    ("checksums", "FileChecksum"),
+    ("licenseConcluded", "LicenseConcluded"),
+    ("licenseInfoInFiles", "LicenseInfoInFile"),
+    ("licenseComments", "LicenseComments"),
+    ("copyrightText", "FileCopyrightText"),
+    ("comment", "FileComment"),
+    ("noticeText", "FileNotice"),
+    ("fileContributors", "FileContributor"),
+    ("attributionTexts", "FileAttributionText"),
+];
+
+const ANNOTATION_FIELDS: &[(&str, &str)] = &[
+    ("annotator", "Annotator"),
+    ("annotationDate", "AnnotationDate"),
+    ("annotationType", "AnnotationType"),
+    ("spdxIdentifierReference", "SPDXREF"),
+    ("comment", "AnnotationComment"),
+];
+
+fn as_items(value: &Value) -> &[Value] {
+    match value {
+        Value::Array(items) => items,
+        Value::Null => &[],
+        other => std::slice::from_ref(other),
+    }
+}
+
+fn write_text<W: Write>(writer: &mut W, tag: &str, text: &str) -> io::Result<()> {
+    if text.contains('\n') {
+        writeln!(writer, "{}: <text>{}</text>", tag, text)
+    } else {
+        writeln!(writer, "{}: {}", tag, text)
+    }
+}
+
+fn write_value<W: Write>(writer: &mut W, tag: &str, value: &Value) -> io::Result<()> {
+    for item in as_items(value) {
+        match item {
+            Value::String(text) => write_text(writer, tag, text)?,
+            other => write_text(writer, tag, &other.to_string())?,
+        }
+    }
+    Ok(())
+}
+
+fn checksum_text(checksum: &Value) -> String {
+    format!(
+        "{}: {}",
+        checksum["algorithm"].as_str().unwrap_or_default(),
+        checksum["checksumValue"].as_str().unwrap_or_default()
+    )
+}
+
+fn write_checksums<W: Write>(writer: &mut W, tag: &str, value: &Value) -> io::Result<()> {
+    for checksum in as_items(value) {
+        write_text(writer, tag, &checksum_text(checksum))?;
+    }
+    Ok(())
+}
+
+fn write_verification_code<W: Write>(writer: &mut W, tag: &str, value: &Value) -> io::Result<()> {
+    if value.is_null() {
+        return Ok(());
+    }
+    let code = value["packageVerificationCodeValue"]
+        .as_str()
+        .unwrap_or_default();
+    let excludes = as_items(&value["packageVerificationCodeExcludedFiles"])
+        .iter()
+        .filter_map(Value::as_str)
+        .collect::<Vec<_>>();
+    if excludes.is_empty() {
+        write_text(writer, tag, code)
+    } else {
+        write_text(
+            writer,
+            tag,
+            &format!("{} (excludes: {})", code, excludes.join(", ")),
+        )
+    }
+}
+
+fn write_external_refs<W: Write>(writer: &mut W, tag: &str, value: &Value) -> io::Result<()> {
+    for reference in as_items(value) {
+        write_text(
+            writer,
+            tag,
+            &format!(
+                "{} {} {}",
+                reference["referenceCategory"].as_str().unwrap_or_default(),
+                reference["referenceType"].as_str().unwrap_or_default(),
+                reference["referenceLocator"].as_str().unwrap_or_default()
+            ),
+        )?;
+        write_value(writer, "ExternalRefComment", &reference["comment"])?;
+    }
+    Ok(())
+}
+
+fn write_external_document_refs<W: Write>(
+    writer: &mut W,
+    tag: &str,
+    value: &Value,
+) -> io::Result<()> {
+    for reference in as_items(value) {
+        write_text(
+            writer,
+            tag,
+            &format!(
+                "{} {} {}",
+                reference["externalDocumentId"].as_str().unwrap_or_default(),
+                reference["spdxDocument"].as_str().unwrap_or_default(),
+                checksum_text(&reference["checksum"])
+            ),
+        )?;
+    }
+    Ok(())
+}
+
+fn write_element<W: Write>(
+    writer: &mut W,
+    element: &Value,
+    fields: &[(&str, &str)],
+) -> io::Result<()> {
+    for (key, tag) in fields {
+        let value = &element[*key];
+        match *key {
+            "checksums" => write_checksums(writer, tag, value)?,
+            "packageVerificationCode" => write_verification_code(writer, tag, value)?,
+            "externalRefs" => write_external_refs(writer, tag, value)?,
+            "externalDocumentRefs" => write_external_document_refs(writer, tag, value)?,
+            _ => write_value(writer, tag, value)?,
+        }
+    }
+    Ok(())
+}
+
+fn element_id(element: &Value) -> &str {
+    element["SPDXID"].as_str().unwrap_or_default()
+}
+
+/// write SPDX JSON document in tag-value format
+///
+/// files contained by a package are written directly after the package,
+/// and all relationships are written explicitly too.
+pub fn write_tag_value<W: Write>(document: &Value, mut writer: W) -> io::Result<()> {
+    write_element(&mut writer, document, DOCUMENT_FIELDS)?;
+    write_element(&mut writer, &document["creationInfo"], CREATION_INFO_FIELDS)?;
+
+    let packages = as_items(&document["packages"]);
+    let files = as_items(&document["files"]);
+    let relationships = as_items(&document["relationships"]);
+
+    // first package which contains the file owns it in tag-value layout
+    let mut owners = HashMap::new();
+    for relationship in relationships {
+        if relationship["relationshipType"].as_str() == Some("CONTAINS") {
+            if let (Some(package_id), Some(file_id)) = (
+                relationship["spdxElementId"].as_str(),
+                relationship["relatedSpdxElement"].as_str(),
+            ) {
+                if packages.iter().any(|p| element_id(p) == package_id) {
+                    owners.entry(file_id).or_insert(package_id);
+                }
+            }
+        }
+    }
+
+    for file in files.iter().filter(|f| !owners.contains_key(element_id(f))) {
+        writeln!(writer)?;
+        write_element(&mut writer, file, FILE_FIELDS)?;
+    }
+    for package in packages {
+        writeln!(writer)?;
+        write_element(&mut writer, package, PACKAGE_FIELDS)?;
+        let package_id = element_id(package);
+        for file in files
+            .iter()
+            .filter(|f| owners.get(element_id(f)) == Some(&package_id))
+        {
+            writeln!(writer)?;
+            write_element(&mut writer, file, FILE_FIELDS)?;
+        }
+    }
+
+    if !relationships.is_empty() {
+        writeln!(writer)?;
+    }
+    for relationship in relationships {
+        write_text(
+            &mut writer,
+            "Relationship",
+            &format!(
+                "{} {} {}",
+                relationship["spdxElementId"].as_str().unwrap_or_default(),
+                relationship["relationshipType"]
+                    .as_str()
+                    .unwrap_or_default(),
+                relationship["relatedSpdxElement"]
+                    .as_str()
+                    .unwrap_or_default()
+            ),
+        )?;
+        write_value(&mut writer, "RelationshipComment", &relationship["comment"])?;
+    }
+
+    for annotation in as_items(&document["annotations"]) {
+        writeln!(writer)?;
+        write_element(&mut writer, annotation, ANNOTATION_FIELDS)?;
+    }
+
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::spdx::{tag_value::write_tag_value, SpdxDocument};
+
+    #[test]
+    fn test_write_golden_document() {
+        let document: Value =
+            serde_json::from_str(include_str!("testdata/document.spdx.json")).unwrap();
+        let mut output = Vec::new();
+        write_tag_value(&document, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            include_str!("testdata/document.spdx")
+        );
+    }
+
+    #[test]
+    fn test_write_spdx_document() {
+        let mut doc = SpdxDocument::new("test");
+        let package = doc.new_package("git");
+        let package_id = package.package_spdx_identifier.clone();
+        doc.push_package(package);
+        let file = doc.new_file("./src/lib.rs");
+        let file_id = file.file_spdx_identifier.clone();
+        doc.push_file(file);
+        doc.push_contains(&package_id, &file_id);
+
+        let mut output = Vec::new();
+        doc.write_tag_value(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("SPDXVersion: SPDX-2."));
+        assert!(output.contains("PackageName: git\n"));
+        assert!(output.contains("FileName: ./src/lib.rs\n"));
+        assert!(output.contains(&format!(
+            "Relationship: {} CONTAINS {}\n",
+            package_id, file_id
+        )));
+    }
+}
@@ -0,0 +1,167 @@
+//! verification of release archives against git
+
+use std::fmt::{self, Display};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Archive {
+    Zip,
+    Tar,
+}
+
+impl Display for Archive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Archive::Zip => write!(f, "zip"),
+            Archive::Tar => write!(f, "tar"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// file in git is not contained in archive
+    MissingInArchive { path: String, archive: Archive },
+    /// file in archive is not contained in git
+    ExtraInArchive { path: String, archive: Archive },
+    /// file content in archive differs from git
+    ChecksumMismatch {
+        path: String,
+        archive: Archive,
+        git: String,
+        archived: String,
+    },
+    /// submodule was requested to be analyzed, but its commit is not available
+    SubmoduleNotAnalyzed { path: String, reason: String },
+}
+
+impl Finding {
+    pub fn path(&self) -> &str {
+        match self {
+            Finding::MissingInArchive { path, .. }
+            | Finding::ExtraInArchive { path, .. }
+            | Finding::ChecksumMismatch { path, .. }
+            | Finding::SubmoduleNotAnalyzed { path, .. } => path,
+        }
+    }
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::MissingInArchive { path, archive } => {
+                write!(f, "missing in {}: {}", archive, path)
+            }
+            Finding::ExtraInArchive { path, archive } => {
+                write!(f, "not in git but in {}: {}", archive, path)
+            }
+            Finding::ChecksumMismatch {
+                path,
+                archive,
+                git,
+                archived,
+            } => write!(
+                f,
+                "checksum mismatch in {}: {} (git: {}, {}: {})",
+                archive, path, git, archive, archived
+            ),
+            Finding::SubmoduleNotAnalyzed { path, reason } => {
+                write!(f, "submodule not analyzed: {} ({})", path, reason)
+            }
+        }
+    }
+}
+
+/// all discrepancies found between git and archives
+#[derive(Debug, Default)]
+pub struct VerificationReport {
+    findings: Vec<Finding>,
+    /// differences which are expected from how archives are created
+    expected: Vec<String>,
+}
+
+impl VerificationReport {
+    pub fn push(&mut self, finding: Finding) {
+        self.findings.push(finding);
+    }
+
+    pub fn push_expected(&mut self, note: String) {
+        self.expected.push(note);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+impl Display for VerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.findings.is_empty() {
+            writeln!(f, "verification passed: archives match git")?;
+        } else {
+            writeln!(
+                f,
+                "verification failed: {} discrepancies found",
+                self.findings.len()
+            )?;
+        }
+        let mut findings = self.findings.iter().collect::<Vec<_>>();
+        findings.sort_by(|a, b| a.path().cmp(b.path()));
+        for finding in findings {
+            writeln!(f, "  {}", finding)?;
+        }
+        for note in &self.expected {
+            writeln!(f, "  expected: {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::verify::{Archive, Finding, VerificationReport};
+
+    #[test]
+    fn test_report_lists_all_findings() {
+        let mut report = VerificationReport::default();
+        assert!(report.is_empty());
+        report.push(Finding::MissingInArchive {
+            path: "./b.rs".to_string(),
+            archive: Archive::Tar,
+        });
+        report.push(Finding::ChecksumMismatch {
+            path: "./a.rs".to_string(),
+            archive: Archive::Zip,
+            git: "1111".to_string(),
+            archived: "2222".to_string(),
+        });
+        report.push(Finding::ExtraInArchive {
+            path: "./c.sh".to_string(),
+            archive: Archive::Zip,
+        });
+        report.push(Finding::SubmoduleNotAnalyzed {
+            path: "./d".to_string(),
+            reason: "fetch failed".to_string(),
+        });
+        assert!(!report.is_empty());
+        assert_eq!(
+            report.to_string(),
+            "verification failed: 4 discrepancies found\n\
+             \x20 checksum mismatch in zip: ./a.rs (git: 1111, zip: 2222)\n\
+             \x20 missing in tar: ./b.rs\n\
+             \x20 not in git but in zip: ./c.sh\n\
+             \x20 submodule not analyzed: ./d (fetch failed)\n"
+        );
+    }
+
+    #[test]
+    fn test_expected_is_not_discrepancy() {
+        let mut report = VerificationReport::default();
+        report.push_expected("submodule ./vendor is not contained in archives".to_string());
+        assert!(report.is_empty());
+        assert_eq!(
+            report.to_string(),
+            "verification passed: archives match git\n\
+             \x20 expected: submodule ./vendor is not contained in archives\n"
+        );
+    }
+}